@@ -0,0 +1,142 @@
+use crate::{Outcome, Transaction, TransactionError};
+use csv::{Reader, StringRecord, Writer};
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
+
+/// Columns preceding and following the [Transaction::FIELDS] of each record
+const PREFIX: [&str; 1] = ["seq"];
+const SUFFIX: [&str; 3] = ["outcome", "error", "hash"];
+
+/// Number of columns of each record
+const COLUMNS: usize = PREFIX.len() + Transaction::FIELDS.len() + SUFFIX.len();
+
+#[derive(thiserror::Error, Debug)]
+pub enum AuditError {
+    #[error(transparent)]
+    Csv(#[from] csv::Error),
+    #[error("Audit log record {0} is malformed")]
+    Malformed(u64),
+    #[error("Audit log record {0} was altered")]
+    Tampered(u64),
+    #[error("Audit log head: {0} doesn't match expected head: {1}")]
+    HeadMismatch(String, String),
+}
+
+/// Hash of a record chained onto the hash of the previous record
+///
+/// Each field is prefixed with its length, such that the hash binds the
+/// contents of every field whatever bytes they hold.
+fn chain(prev: &[u8; 32], fields: &[&str]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(prev);
+    for field in fields {
+        hasher.update((field.len() as u64).to_le_bytes());
+        hasher.update(field.as_bytes());
+    }
+    hasher.finalize().into()
+}
+
+/// Tamper-evident log of every transaction applied to an
+/// [Authority](crate::Authority), accepted or rejected
+///
+/// Each record carries the SHA-256 hash of its fields chained onto the hash of
+/// the previous record, such that altering, removing or reordering any record
+/// changes every subsequent hash, including the final head hash.
+pub struct AuditLog<W: Write> {
+    wtr: Writer<W>,
+    seq: u64,
+    head: [u8; 32],
+}
+
+impl<W: Write> AuditLog<W> {
+    pub fn new(writer: W) -> Result<Self, AuditError> {
+        let mut wtr = Writer::from_writer(writer);
+        wtr.write_record(PREFIX.iter().chain(&Transaction::FIELDS).chain(&SUFFIX))?;
+
+        Ok(Self {
+            wtr,
+            seq: 0,
+            head: [0; 32],
+        })
+    }
+
+    /// Records the outcome of applying `t`
+    pub fn record(
+        &mut self,
+        t: &Transaction,
+        res: &Result<Outcome, TransactionError>,
+    ) -> Result<(), AuditError> {
+        self.seq += 1;
+
+        let (outcome, error) = match res {
+            Ok(Outcome::Applied) => ("accepted", String::new()),
+            Ok(Outcome::Duplicate) => ("duplicate", String::new()),
+            Err(e) => ("rejected", e.to_string()),
+        };
+
+        let seq = self.seq.to_string();
+        let transaction = t.fields();
+        let fields = [seq.as_str()]
+            .into_iter()
+            .chain(transaction.iter().map(String::as_str))
+            .chain([outcome, error.as_str()])
+            .collect::<Vec<_>>();
+
+        self.head = chain(&self.head, &fields);
+        let hash = hex::encode(self.head);
+
+        self.wtr
+            .write_record(fields.iter().copied().chain([hash.as_str()]))?;
+        Ok(())
+    }
+
+    /// Hash of the latest record
+    pub fn head(&self) -> String {
+        hex::encode(self.head)
+    }
+
+    pub fn flush(&mut self) -> Result<(), AuditError> {
+        self.wtr.flush().map_err(csv::Error::from)?;
+        Ok(())
+    }
+
+    /// Flushes the log and returns the underlying writer
+    pub fn into_inner(self) -> Result<W, AuditError> {
+        self.wtr
+            .into_inner()
+            .map_err(|e| csv::Error::from(e.into_error()).into())
+    }
+}
+
+/// Re-walks an audit log verifying the hash chain, returning its head hash
+///
+/// When `expected_head` is given, the log must also end in that head, which
+/// detects records being truncated from the end of the log.
+pub fn verify_audit<R: Read>(
+    rdr: Reader<R>,
+    expected_head: Option<&str>,
+) -> Result<String, AuditError> {
+    let mut head = [0; 32];
+
+    for (i, record) in rdr.into_records().enumerate() {
+        let seq = i as u64 + 1;
+        let record: StringRecord = record?;
+        if record.len() != COLUMNS || record[0] != seq.to_string() {
+            return Err(AuditError::Malformed(seq));
+        }
+
+        let fields = record.iter().take(COLUMNS - 1).collect::<Vec<_>>();
+        head = chain(&head, &fields);
+        if hex::encode(head) != record[COLUMNS - 1] {
+            return Err(AuditError::Tampered(seq));
+        }
+    }
+
+    let head = hex::encode(head);
+    match expected_head {
+        Some(expected) if expected != head => {
+            Err(AuditError::HeadMismatch(head, expected.to_string()))
+        }
+        _ => Ok(head),
+    }
+}
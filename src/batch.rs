@@ -0,0 +1,228 @@
+use crate::{
+    limits::Usage, Account, AccountBalance, AccountTransaction, Authority, Client, ClientId,
+    Collection, DisputeTransaction, Escrow, OperationTransaction, Outcome, Transaction,
+    TransactionError, TransferTransaction, TxId,
+};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    io,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum BatchError {
+    #[error("Batch rolled back, transaction {0} rejected: {1}")]
+    Rejected(usize, TransactionError),
+    /// The batch could not be rolled back
+    #[error(transparent)]
+    Storage(#[from] io::Error),
+}
+
+/// State of a client prior to a batch
+struct ClientImage {
+    client: Option<Client>,
+    usage: Option<Usage>,
+    available: Option<AccountBalance>,
+    held: Option<AccountBalance>,
+    debt: Option<AccountBalance>,
+    collection: Option<Collection>,
+}
+
+/// Prior state of everything changed by a batch, saved before each of its
+/// transactions is applied and restored should any be rejected
+///
+/// Only the first image of each client and ledger entry is kept, as it is
+/// the state prior to the batch.
+struct Savepoint {
+    clients: HashMap<ClientId, ClientImage>,
+    transactions: HashMap<TxId, Option<OperationTransaction>>,
+    disputes: HashMap<TxId, Option<DisputeTransaction>>,
+    escrows: HashMap<TxId, Option<Escrow>>,
+    transfers: HashMap<TxId, Option<TransferTransaction>>,
+    accounts: HashMap<TxId, Option<AccountTransaction>>,
+    dispute_history: usize,
+    settlement: Option<AccountBalance>,
+    chargeback_loss: Option<AccountBalance>,
+    escrow: Option<AccountBalance>,
+    entries: usize,
+    compactor: u64,
+    deadlines: usize,
+    clock: Option<u64>,
+}
+
+impl Savepoint {
+    fn new(a: &Authority) -> Self {
+        Self {
+            clients: HashMap::new(),
+            transactions: HashMap::new(),
+            disputes: HashMap::new(),
+            escrows: HashMap::new(),
+            transfers: HashMap::new(),
+            accounts: HashMap::new(),
+            dispute_history: a.dispute_history.len(),
+            settlement: a.journal.account(Account::Settlement),
+            chargeback_loss: a.journal.account(Account::ChargebackLoss),
+            escrow: a.journal.account(Account::Escrow),
+            entries: a.journal.entries_len(),
+            compactor: a.compactor.savepoint(),
+            deadlines: a.deadlines.as_ref().map_or(0, |d| d.len()),
+            clock: a.clock,
+        }
+    }
+
+    fn save_client(&mut self, a: &Authority, client: ClientId) {
+        self.clients.entry(client).or_insert_with(|| ClientImage {
+            client: a.client_state.get(&client).cloned(),
+            usage: a.limits.usage(client),
+            available: a.journal.account(Account::ClientAvailable(client)),
+            held: a.journal.account(Account::ClientHeld(client)),
+            debt: a.journal.account(Account::ClientDebt(client)),
+            collection: a.collections.as_ref().and_then(|c| c.account(client)),
+        });
+    }
+
+    /// Saves escrow `tx` along with both of its parties
+    fn save_escrow(&mut self, a: &Authority, tx: TxId) {
+        let escrow = a.escrows.get(&tx).cloned();
+        if let Some(escrow) = &escrow {
+            self.save_client(a, escrow.payer());
+            self.save_client(a, escrow.payee());
+        }
+        self.escrows.entry(tx).or_insert(escrow);
+    }
+
+    /// Saves the state `t` may change
+    fn save(&mut self, a: &Authority, t: &Transaction) -> io::Result<()> {
+        match t {
+            Transaction::Operation(o) => {
+                self.save_client(a, o.client());
+                if let Entry::Vacant(entry) = self.transactions.entry(o.tx()) {
+                    entry.insert(a.transaction_ledger.get(&o.tx())?);
+                }
+            }
+            Transaction::Dispute(d) => {
+                // Disputes change the state of the disputed transaction's
+                // owner rather than their issuer, and reversals the disputed
+                // transaction itself
+                if let Some(disputed) = a.transaction_ledger.get(&d.tx())? {
+                    self.save_client(a, disputed.client());
+                    self.transactions.entry(d.tx()).or_insert(Some(disputed));
+                } else if a.escrows.contains_key(&d.tx()) {
+                    self.save_escrow(a, d.tx());
+                }
+                if let Entry::Vacant(entry) = self.disputes.entry(d.tx()) {
+                    entry.insert(a.dispute_ledger.get(&d.tx())?);
+                }
+            }
+            Transaction::Account(t) => {
+                self.save_client(a, t.client());
+                self.accounts
+                    .entry(t.tx())
+                    .or_insert_with(|| a.accounts.get(&t.tx()).cloned());
+            }
+            Transaction::Transfer(t) => {
+                self.save_client(a, t.client());
+                self.transfers
+                    .entry(t.tx())
+                    .or_insert_with(|| a.transfers.get(&t.tx()).cloned());
+            }
+            Transaction::Escrow(e) => {
+                self.save_client(a, e.client());
+                self.save_escrow(a, e.tx());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn rollback(self, a: &mut Authority) -> io::Result<()> {
+        for (id, image) in self.clients {
+            match image.client {
+                Some(client) => a.client_state.insert(id, client),
+                None => a.client_state.remove(&id),
+            };
+            a.limits.restore_usage(id, image.usage);
+            a.journal
+                .restore(Account::ClientAvailable(id), image.available);
+            a.journal.restore(Account::ClientHeld(id), image.held);
+            a.journal.restore(Account::ClientDebt(id), image.debt);
+            if let Some(collections) = &mut a.collections {
+                collections.restore(id, image.collection);
+            }
+        }
+
+        for (tx, t) in self.transactions {
+            match t {
+                Some(t) => a.transaction_ledger.insert(tx, t)?,
+                None => a.transaction_ledger.remove(&tx)?,
+            }
+        }
+        for (tx, t) in self.disputes {
+            match t {
+                Some(t) => a.dispute_ledger.insert(tx, t)?,
+                None => a.dispute_ledger.remove(&tx)?,
+            }
+        }
+        for (tx, escrow) in self.escrows {
+            match escrow {
+                Some(escrow) => a.escrows.insert(tx, escrow),
+                None => a.escrows.remove(&tx),
+            };
+        }
+        for (tx, t) in self.transfers {
+            match t {
+                Some(t) => a.transfers.insert(tx, t),
+                None => a.transfers.remove(&tx),
+            };
+        }
+        for (tx, t) in self.accounts {
+            match t {
+                Some(t) => a.accounts.insert(tx, t),
+                None => a.accounts.remove(&tx),
+            };
+        }
+
+        a.dispute_history.truncate(self.dispute_history);
+        a.journal.restore(Account::Settlement, self.settlement);
+        a.journal
+            .restore(Account::ChargebackLoss, self.chargeback_loss);
+        a.journal.restore(Account::Escrow, self.escrow);
+        a.journal.truncate(self.entries);
+        a.compactor.rollback(self.compactor);
+        if let Some(deadlines) = &mut a.deadlines {
+            deadlines.truncate(self.deadlines);
+        }
+        a.clock = self.clock;
+
+        Ok(())
+    }
+}
+
+impl Authority {
+    /// Applies every transaction of `batch`, or none at all
+    ///
+    /// Should any transaction be rejected, every change made by the preceding
+    /// transactions of the batch, to clients and to the transaction and
+    /// dispute ledgers, is rolled back. Duplicates are not rejections.
+    pub fn apply_batch(&mut self, batch: &[Transaction]) -> Result<Vec<Outcome>, BatchError> {
+        let mut savepoint = Savepoint::new(self);
+        let mut outcomes = Vec::with_capacity(batch.len());
+
+        for (i, t) in batch.iter().enumerate() {
+            savepoint.save(self, t)?;
+
+            match self.apply_transaction(t.clone()) {
+                Ok(outcome) => outcomes.push(outcome),
+                Err(e) => {
+                    savepoint.rollback(self)?;
+                    return Err(BatchError::Rejected(i, e));
+                }
+            }
+        }
+
+        // Settling disputes and compaction are deferred until the batch is
+        // committed, as neither could be rolled back
+        self.settle_expired()?;
+        self.compact()?;
+        Ok(outcomes)
+    }
+}
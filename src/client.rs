@@ -1,19 +1,40 @@
-use crate::{DisputeError, OperationError, OperationTransaction, OperationTransactionType};
+use crate::{
+    ClientId, DisputeError, KycStatus, OperationError, OperationTransaction,
+    OperationTransactionType, TransferTransaction, TxId, Wallet, WalletId,
+};
 use rust_decimal::Decimal;
 use serde::Serialize;
+use std::collections::BTreeMap;
 
-#[derive(Debug, PartialEq, Eq, Serialize)]
+/// Zero at the scale of balances, such that balances left unchanged by an
+/// adjustment keep their scale
+const ZERO: Decimal = Decimal::from_parts(0, 0, 0, false, 4);
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
 pub struct Client {
     #[serde(rename = "client")]
-    id: u16,
+    id: ClientId,
     available: Decimal,
     held: Decimal,
     total: Decimal,
     locked: bool,
+    /// Negative available balance moved into collections, which is left out
+    /// of `available` but not `total`
+    #[serde(skip)]
+    debt: Decimal,
+    #[serde(skip)]
+    closed: bool,
+    /// Verification status, where tracked, which must be verified to withdraw
+    #[serde(skip)]
+    kyc: Option<KycStatus>,
+    /// Breakdown of the balances by wallet, always including the default
+    /// wallet
+    #[serde(skip)]
+    wallets: BTreeMap<WalletId, Wallet>,
 }
 
 impl Client {
-    pub fn new(id: u16) -> Self {
+    pub fn new(id: ClientId) -> Self {
         Self {
             id,
             // Should create specialized type that enforces scale invariant
@@ -21,10 +42,14 @@ impl Client {
             held: Decimal::new(0, 4),
             total: Decimal::new(0, 4),
             locked: false,
+            debt: Decimal::new(0, 4),
+            closed: false,
+            kyc: None,
+            wallets: BTreeMap::from([(0, Wallet::new(id, 0))]),
         }
     }
 
-    pub fn id(&self) -> u16 {
+    pub fn id(&self) -> ClientId {
         self.id
     }
 
@@ -43,6 +68,77 @@ impl Client {
     pub fn locked(&self) -> bool {
         self.locked
     }
+
+    pub fn debt(&self) -> &Decimal {
+        &self.debt
+    }
+
+    pub fn closed(&self) -> bool {
+        self.closed
+    }
+
+    pub fn kyc(&self) -> Option<KycStatus> {
+        self.kyc
+    }
+
+    pub fn wallet(&self, wallet: WalletId) -> Option<&Wallet> {
+        self.wallets.get(&wallet)
+    }
+
+    /// Wallets of the client, in order of wallet
+    pub fn wallets(&self) -> impl Iterator<Item = &Wallet> {
+        self.wallets.values()
+    }
+
+    fn wallet_mut(&mut self, wallet: WalletId) -> &mut Wallet {
+        let id = self.id;
+        self.wallets
+            .entry(wallet)
+            .or_insert_with(|| Wallet::new(id, wallet))
+    }
+
+    /// Adds the given changes to each balance of both the client and `wallet`
+    fn adjust(&mut self, wallet: WalletId, available: Decimal, held: Decimal, total: Decimal) {
+        self.available += available;
+        self.held += held;
+        self.total += total;
+        self.wallet_mut(wallet).adjust(available, held, total);
+    }
+
+    pub(crate) fn set_kyc(&mut self, kyc: KycStatus) {
+        self.kyc = Some(kyc);
+    }
+
+    /// Whether the account has outstanding debt in collections
+    pub fn in_collections(&self) -> bool {
+        self.debt > Decimal::ZERO
+    }
+
+    /// Moves `amount` of a negative available balance of `wallet` into debt
+    pub(crate) fn incur_debt(&mut self, wallet: WalletId, amount: Decimal) {
+        self.available += amount;
+        self.debt += amount;
+        self.wallet_mut(wallet).adjust_debt(amount);
+    }
+
+    /// Repays `amount` of the debt of `wallet` out of its available balance
+    pub(crate) fn repay_debt(&mut self, wallet: WalletId, amount: Decimal) {
+        self.available -= amount;
+        self.debt -= amount;
+        self.wallet_mut(wallet).adjust_debt(-amount);
+    }
+
+    /// Whether the account holds no funds and owes nothing
+    pub(crate) fn is_empty(&self) -> bool {
+        self.available.is_zero()
+            && self.held.is_zero()
+            && self.total.is_zero()
+            && self.debt.is_zero()
+    }
+
+    pub(crate) fn set_closed(&mut self, closed: bool) {
+        self.closed = closed;
+    }
 }
 
 impl Client {
@@ -54,85 +150,162 @@ impl Client {
         if self.locked {
             return Err(OperationError::Locked(self.id));
         }
+        if self.closed {
+            return Err(OperationError::Closed(self.id));
+        }
 
         let amount = t.amount();
         match t.transaction_type() {
             OperationTransactionType::Deposit => {
-                self.available += amount;
-                self.total += amount;
+                self.adjust(t.wallet(), amount, ZERO, amount);
             }
             OperationTransactionType::Withdrawal => {
-                if self.available < amount {
-                    return Err(OperationError::WithdrawExceeded(
-                        t.tx(),
-                        amount,
-                        self.available,
-                    ));
-                }
-
-                self.available -= amount;
-                self.total -= amount;
+                self.check_withdrawal(t.tx(), t.wallet(), amount)?;
+                self.adjust(t.wallet(), -amount, ZERO, -amount);
             }
         }
 
         Ok(())
     }
 
-    /// Applies a dispute transaction to the client
-    pub fn apply_dispute(&mut self, t: &OperationTransaction) -> Result<(), DisputeError> {
-        let amount = t.amount();
-        self.held += amount;
+    /// Checks `amount` may be withdrawn out of `wallet` by transaction `tx`
+    fn check_withdrawal(
+        &self,
+        tx: TxId,
+        wallet: WalletId,
+        amount: Decimal,
+    ) -> Result<(), OperationError> {
+        if self.kyc.is_some_and(|kyc| kyc != KycStatus::Verified) {
+            return Err(OperationError::Unverified(tx, self.id));
+        }
+        // Neither the wallet nor the client as a whole may be overdrawn
+        let available = self
+            .wallet(wallet)
+            .map_or(Decimal::ZERO, |w| *w.available())
+            .min(self.available);
+        if available < amount {
+            return Err(OperationError::WithdrawExceeded(tx, amount, available));
+        }
+
+        Ok(())
+    }
+
+    /// Moves `amount` out of the available balance of `wallet` into escrow
+    pub fn apply_escrow(
+        &mut self,
+        tx: TxId,
+        wallet: WalletId,
+        amount: Decimal,
+    ) -> Result<(), OperationError> {
+        if self.locked {
+            return Err(OperationError::Locked(self.id));
+        }
+        if self.closed {
+            return Err(OperationError::Closed(self.id));
+        }
+
+        self.check_withdrawal(tx, wallet, amount)?;
+        self.adjust(wallet, -amount, ZERO, -amount);
+
+        Ok(())
+    }
+
+    /// Credits `amount` released or refunded out of escrow to `wallet`
+    pub fn apply_escrow_credit(&mut self, wallet: WalletId, amount: Decimal) {
+        self.adjust(wallet, amount, ZERO, amount);
+    }
+
+    /// Applies a dispute of `amount` of a transaction to the client
+    pub fn apply_dispute(
+        &mut self,
+        t: &OperationTransaction,
+        amount: Decimal,
+    ) -> Result<(), DisputeError> {
+        if self.closed {
+            return Err(DisputeError::Closed(self.id));
+        }
 
         match t.transaction_type() {
             OperationTransactionType::Deposit => {
                 // It is valid to potentially go into the negative as a deposit
                 // transaction can always be disputed
-                self.available -= amount;
+                self.adjust(t.wallet(), -amount, amount, ZERO);
             }
             OperationTransactionType::Withdrawal => {
-                self.total += amount;
+                self.adjust(t.wallet(), ZERO, amount, amount);
             }
         }
 
         Ok(())
     }
 
-    /// Applies a dispute resolve transaction to the client
-    pub fn apply_resolve(&mut self, t: &OperationTransaction) -> Result<(), DisputeError> {
-        let amount = t.amount();
-
+    /// Applies a dispute resolve transaction, releasing `amount` held, to the
+    /// client
+    pub fn apply_resolve(
+        &mut self,
+        t: &OperationTransaction,
+        amount: Decimal,
+    ) -> Result<(), DisputeError> {
         debug_assert!(self.held >= amount);
-        self.held -= amount;
 
         match t.transaction_type() {
             OperationTransactionType::Deposit => {
-                self.available += amount;
+                self.adjust(t.wallet(), amount, -amount, ZERO);
             }
             OperationTransactionType::Withdrawal => {
-                self.total -= amount;
+                self.adjust(t.wallet(), ZERO, -amount, -amount);
             }
         }
 
         Ok(())
     }
 
-    /// Applies a transaction chargeback to the client
-    pub fn apply_chargeback(&mut self, t: &OperationTransaction) -> Result<(), DisputeError> {
+    /// Applies the exact inverse of `amount` of a deposit or withdrawal
+    /// transaction to the client
+    pub fn apply_reversal(
+        &mut self,
+        t: &OperationTransaction,
+        amount: Decimal,
+    ) -> Result<(), DisputeError> {
         if self.locked {
             return Err(DisputeError::Locked(self.id));
         }
+        if self.closed {
+            return Err(DisputeError::Closed(self.id));
+        }
 
-        let amount = t.amount();
+        match t.transaction_type() {
+            OperationTransactionType::Deposit => {
+                // As with disputes, a reversed deposit may have already been
+                // withdrawn
+                self.adjust(t.wallet(), -amount, ZERO, -amount);
+            }
+            OperationTransactionType::Withdrawal => {
+                self.adjust(t.wallet(), amount, ZERO, amount);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Applies a chargeback of `amount` of a transaction to the client
+    pub fn apply_chargeback(
+        &mut self,
+        t: &OperationTransaction,
+        amount: Decimal,
+    ) -> Result<(), DisputeError> {
+        if self.locked {
+            return Err(DisputeError::Locked(self.id));
+        }
 
         debug_assert!(self.held >= amount);
-        self.held -= amount;
 
         match t.transaction_type() {
             OperationTransactionType::Deposit => {
-                self.total -= amount;
+                self.adjust(t.wallet(), ZERO, -amount, -amount);
             }
             OperationTransactionType::Withdrawal => {
-                self.available += amount;
+                self.adjust(t.wallet(), amount, -amount, ZERO);
             }
         }
 
@@ -140,17 +313,56 @@ impl Client {
 
         Ok(())
     }
+
+    /// Applies an internal move between two wallets of the client, leaving
+    /// the client's own balances unchanged
+    pub fn apply_transfer(&mut self, t: &TransferTransaction) -> Result<(), OperationError> {
+        if self.locked {
+            return Err(OperationError::Locked(self.id));
+        }
+        if self.closed {
+            return Err(OperationError::Closed(self.id));
+        }
+
+        let amount = t.amount();
+        if amount <= Decimal::ZERO || t.from() == t.to() {
+            return Err(OperationError::InvalidTransfer(t.tx()));
+        }
+        let available = self
+            .wallet(t.from())
+            .map_or(Decimal::ZERO, |w| *w.available());
+        if available < amount {
+            return Err(OperationError::WithdrawExceeded(t.tx(), amount, available));
+        }
+
+        self.wallet_mut(t.from()).adjust(-amount, ZERO, -amount);
+        self.wallet_mut(t.to()).adjust(amount, ZERO, amount);
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 impl Client {
-    pub fn test(id: u16, available: i64, held: i64, total: i64, locked: bool) -> Self {
+    pub fn test(id: ClientId, available: i64, held: i64, total: i64, locked: bool) -> Self {
         Self {
             id,
             available: Decimal::from(available),
             held: Decimal::from(held),
             total: Decimal::from(total),
             locked,
+            debt: Decimal::new(0, 4),
+            closed: false,
+            kyc: None,
+            wallets: BTreeMap::from([(0, {
+                let mut wallet = Wallet::new(id, 0);
+                wallet.adjust(
+                    Decimal::from(available),
+                    Decimal::from(held),
+                    Decimal::from(total),
+                );
+                wallet
+            })]),
         }
     }
 }
@@ -0,0 +1,189 @@
+use crate::{Authority, ClientId, TxId, WalletId};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::{collections::BTreeMap, str::FromStr};
+
+/// Share of each deposit repaying the debt of an account in collections
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum CollectionPolicy {
+    /// Deposits repay the debt in full before any is made available
+    #[default]
+    Full,
+    /// A fraction, between 0 and 1, of each deposit repays the debt, the
+    /// remainder being made available straight away
+    Share(Decimal),
+}
+
+impl CollectionPolicy {
+    /// Part of a deposit of `amount` owed towards the debt
+    fn repayment(&self, amount: Decimal) -> Decimal {
+        match self {
+            CollectionPolicy::Full => amount,
+            CollectionPolicy::Share(share) => (amount * share).round_dp(4),
+        }
+    }
+}
+
+impl FromStr for CollectionPolicy {
+    type Err = String;
+
+    /// Parses either `full` or a fraction of each deposit, such as `0.5`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "full" {
+            return Ok(CollectionPolicy::Full);
+        }
+
+        match Decimal::from_str(s) {
+            Ok(share) if share > Decimal::ZERO && share <= Decimal::ONE => {
+                Ok(CollectionPolicy::Share(share))
+            }
+            _ => Err(format!("Invalid collection policy: {}", s)),
+        }
+    }
+}
+
+/// Row of the collections report, for every account which has been in
+/// collections
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Collection {
+    client: ClientId,
+    /// Debt yet to be repaid
+    outstanding: Decimal,
+    /// Debt incurred in total
+    incurred: Decimal,
+    /// Debt repaid in total
+    recovered: Decimal,
+    /// Latest timestamp seen when the account last entered collections
+    since: Option<u64>,
+}
+
+impl Collection {
+    fn new(client: ClientId) -> Self {
+        Self {
+            client,
+            outstanding: Decimal::new(0, 4),
+            incurred: Decimal::new(0, 4),
+            recovered: Decimal::new(0, 4),
+            since: None,
+        }
+    }
+
+    pub fn client(&self) -> ClientId {
+        self.client
+    }
+
+    pub fn outstanding(&self) -> Decimal {
+        self.outstanding
+    }
+
+    pub fn incurred(&self) -> Decimal {
+        self.incurred
+    }
+
+    pub fn recovered(&self) -> Decimal {
+        self.recovered
+    }
+
+    pub fn since(&self) -> Option<u64> {
+        self.since
+    }
+}
+
+/// Accounts which have gone into collections, by client
+pub(crate) struct Collections {
+    policy: CollectionPolicy,
+    accounts: BTreeMap<ClientId, Collection>,
+}
+
+impl Collections {
+    pub(crate) fn new(policy: CollectionPolicy) -> Self {
+        Self {
+            policy,
+            accounts: BTreeMap::new(),
+        }
+    }
+
+    /// Collection of `client`, as saved by a batch
+    pub(crate) fn account(&self, client: ClientId) -> Option<Collection> {
+        self.accounts.get(&client).cloned()
+    }
+
+    /// Restores the collection of `client` previously saved by a batch
+    pub(crate) fn restore(&mut self, client: ClientId, collection: Option<Collection>) {
+        match collection {
+            Some(collection) => self.accounts.insert(client, collection),
+            None => self.accounts.remove(&client),
+        };
+    }
+}
+
+impl Authority {
+    /// Moves a negative available balance of the `wallet` of `client` into
+    /// debt, or repays the wallet's debt out of funds made available by
+    /// transaction `tx`
+    ///
+    /// `before` is the wallet's available balance prior to the transaction.
+    /// Only deposits are subject to the collection policy, whereas funds
+    /// returned through disputes repay the debt in full.
+    pub(crate) fn collect(
+        &mut self,
+        client: ClientId,
+        wallet: WalletId,
+        tx: TxId,
+        before: Decimal,
+        deposit: bool,
+    ) {
+        let Some(collections) = &mut self.collections else {
+            return;
+        };
+        // Client and wallet must exist as they were just applied a transaction
+        let client = self.client_state.get_mut(&client).unwrap();
+        let (available, debt) = client
+            .wallet(wallet)
+            .map(|w| (*w.available(), *w.debt()))
+            .unwrap();
+
+        if available < Decimal::ZERO {
+            let amount = -available;
+            client.incur_debt(wallet, amount);
+            self.journal.debt(tx, client.id(), amount);
+
+            let collection = collections
+                .accounts
+                .entry(client.id())
+                .or_insert_with(|| Collection::new(client.id()));
+            if collection.outstanding.is_zero() {
+                collection.since = self.clock;
+            }
+            collection.outstanding += amount;
+            collection.incurred += amount;
+        } else if available > before && debt > Decimal::ZERO {
+            let credit = available - before;
+            let repayment = match deposit {
+                true => collections.policy.repayment(credit),
+                false => credit,
+            }
+            .min(debt);
+            if repayment.is_zero() {
+                return;
+            }
+
+            client.repay_debt(wallet, repayment);
+            self.journal.repayment(tx, client.id(), repayment);
+
+            // Clients with debt are always in collections
+            let collection = collections.accounts.get_mut(&client.id()).unwrap();
+            collection.outstanding -= repayment;
+            collection.recovered += repayment;
+        }
+    }
+
+    /// Reports every account which has been in collections, in order of
+    /// client
+    pub fn collections(&self) -> Vec<Collection> {
+        self.collections
+            .iter()
+            .flat_map(|collections| collections.accounts.values().cloned())
+            .collect()
+    }
+}
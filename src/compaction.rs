@@ -0,0 +1,193 @@
+use crate::{OperationTransaction, TxId};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BinaryHeap, HashSet, VecDeque},
+};
+
+/// Compact set of transaction ids
+///
+/// Numeric ids are stored as disjoint inclusive ranges, such that the mostly
+/// sequential ids issued upstream take up a handful of entries regardless of
+/// how many transactions were processed. UUIDs, having no such order, are
+/// stored individually.
+#[derive(Default)]
+pub(crate) struct TxSet {
+    ranges: BTreeMap<u64, u64>,
+    uuids: HashSet<u128>,
+}
+
+impl TxSet {
+    pub(crate) fn contains(&self, tx: TxId) -> bool {
+        match tx {
+            TxId::Number(tx) => self
+                .ranges
+                .range(..=tx)
+                .next_back()
+                .is_some_and(|(_, &end)| tx <= end),
+            TxId::Uuid(uuid) => self.uuids.contains(&uuid),
+        }
+    }
+
+    pub(crate) fn insert(&mut self, tx: TxId) {
+        if self.contains(tx) {
+            return;
+        }
+
+        let tx = match tx {
+            TxId::Number(tx) => tx,
+            TxId::Uuid(uuid) => {
+                self.uuids.insert(uuid);
+                return;
+            }
+        };
+
+        let mut start = tx;
+        let mut end = tx;
+
+        // Merge with the range ending right before tx
+        if let Some((&s, _)) = self
+            .ranges
+            .range(..tx)
+            .next_back()
+            .filter(|(_, &e)| e.checked_add(1) == Some(tx))
+        {
+            self.ranges.remove(&s);
+            start = s;
+        }
+
+        // Merge with the range starting right after tx
+        if let Some(next) = tx.checked_add(1) {
+            if let Some(e) = self.ranges.remove(&next) {
+                end = e;
+            }
+        }
+
+        self.ranges.insert(start, end);
+    }
+}
+
+/// Tracks ledgered transactions and evicts them once they can no longer be
+/// disputed, either due to the dispute window or the maximum transaction age
+/// (in ledgered transactions) elapsing.
+///
+/// Transactions are queued both in the order they were ledgered, which is the
+/// order they age in, and by timestamp, which is the order their dispute
+/// window expires in, such that out of order timestamps never hold back
+/// transactions already due.
+///
+/// Only ids of evicted transactions are retained, in a [TxSet], so that their
+/// reuse is still detected. Resubmissions of evicted transactions cannot be
+/// told apart from conflicting ones and are rejected either way.
+#[derive(Default)]
+pub(crate) struct Compactor {
+    max_age: Option<u64>,
+    seq: u64,
+    /// Transactions by sequence number, when a maximum age is set
+    by_seq: VecDeque<(u64, TxId)>,
+    /// Timestamped transactions by earliest timestamp, when a dispute window
+    /// is set
+    by_time: BinaryHeap<Reverse<(u64, u64, TxId)>>,
+    /// Transactions due for eviction while under dispute
+    deferred: HashSet<TxId>,
+    evicted: TxSet,
+}
+
+impl Compactor {
+    pub(crate) fn set_max_age(&mut self, max_age: u64) {
+        self.max_age = Some(max_age);
+    }
+
+    /// Sequence number, as saved by a batch
+    pub(crate) fn savepoint(&self) -> u64 {
+        self.seq
+    }
+
+    /// Untracks transactions tracked since `savepoint`, provided no
+    /// compaction took place in between
+    pub(crate) fn rollback(&mut self, seq: u64) {
+        self.seq = seq;
+        self.by_seq.retain(|&(s, _)| s <= seq);
+        self.by_time.retain(|Reverse((_, s, _))| *s <= seq);
+    }
+
+    /// Number of transactions ledgered so far
+    pub(crate) fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    pub(crate) fn evicted(&self, tx: TxId) -> bool {
+        self.evicted.contains(tx)
+    }
+
+    /// Tracks a newly ledgered transaction
+    pub(crate) fn track(&mut self, t: &OperationTransaction, window: Option<u64>) {
+        self.seq += 1;
+
+        if self.max_age.is_some() {
+            self.by_seq.push_back((self.seq, t.tx()));
+        }
+        // Transactions without a timestamp may be disputed indefinitely
+        if let (Some(_), Some(timestamp)) = (window, t.timestamp()) {
+            self.by_time.push(Reverse((timestamp, self.seq, t.tx())));
+        }
+    }
+
+    /// Evicts transactions which can no longer be disputed
+    ///
+    /// Transactions under dispute are deferred until their dispute is closed.
+    pub(crate) fn compact<D, E>(
+        &mut self,
+        window: Option<u64>,
+        clock: Option<u64>,
+        disputed: D,
+        mut evict: E,
+    ) where
+        D: Fn(TxId) -> bool,
+        E: FnMut(TxId),
+    {
+        let evicted = &mut self.evicted;
+        self.deferred.retain(|&tx| {
+            if disputed(tx) {
+                return true;
+            }
+
+            evicted.insert(tx);
+            evict(tx);
+            false
+        });
+
+        let mut due = vec![];
+        if let Some(max_age) = self.max_age {
+            while let Some(&(seq, tx)) = self.by_seq.front() {
+                if self.seq - seq < max_age {
+                    break;
+                }
+                self.by_seq.pop_front();
+                due.push(tx);
+            }
+        }
+        if let (Some(window), Some(now)) = (window, clock) {
+            while let Some(&Reverse((issued, _, tx))) = self.by_time.peek() {
+                if now.saturating_sub(issued) <= window {
+                    break;
+                }
+                self.by_time.pop();
+                due.push(tx);
+            }
+        }
+
+        // Transactions may be due in both orders
+        for tx in due {
+            if self.evicted.contains(tx) || self.deferred.contains(&tx) {
+                continue;
+            }
+
+            if disputed(tx) {
+                self.deferred.insert(tx);
+            } else {
+                self.evicted.insert(tx);
+                evict(tx);
+            }
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use flate2::{bufread::MultiGzDecoder, write::GzEncoder};
+use std::{
+    fmt,
+    io::{self, BufRead, BufReader, Write},
+    path::Path,
+    str::FromStr,
+};
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// Compression format of input and output files
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// Format indicated by the extension of `path`
+    pub fn from_extension<P>(path: P) -> Option<Self>
+    where
+        P: AsRef<Path>,
+    {
+        match path.as_ref().extension()?.to_str()? {
+            "gz" => Some(Compression::Gzip),
+            "zst" => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+
+    /// Format indicated by the leading magic bytes of `data`
+    pub fn from_magic(data: &[u8]) -> Option<Self> {
+        if data.starts_with(GZIP_MAGIC) {
+            Some(Compression::Gzip)
+        } else if data.starts_with(ZSTD_MAGIC) {
+            Some(Compression::Zstd)
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Compression::Gzip => write!(f, "gzip"),
+            Compression::Zstd => write!(f, "zstd"),
+        }
+    }
+}
+
+impl FromStr for Compression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gzip" | "gz" => Ok(Compression::Gzip),
+            "zstd" | "zst" => Ok(Compression::Zstd),
+            _ => Err(format!("Unknown compression: {}", s)),
+        }
+    }
+}
+
+/// Wraps `input` in a decoder for its compression format, detected from its
+/// magic bytes or failing that the extension of `path`
+///
+/// Uncompressed input is returned as is.
+pub fn decompress<'a, R>(mut input: R, path: Option<&Path>) -> io::Result<Box<dyn BufRead + 'a>>
+where
+    R: BufRead + 'a,
+{
+    let compression = Compression::from_magic(input.fill_buf()?)
+        .or_else(|| path.and_then(Compression::from_extension));
+
+    Ok(match compression {
+        Some(Compression::Gzip) => Box::new(BufReader::new(MultiGzDecoder::new(input))),
+        Some(Compression::Zstd) => Box::new(BufReader::new(zstd::Decoder::with_buffer(input)?)),
+        None => Box::new(input),
+    })
+}
+
+/// Writer optionally compressing everything written through it
+///
+/// Compressed output is only complete once [finished](Encoder::finish).
+pub enum Encoder<W: Write> {
+    Plain(W),
+    Gzip(GzEncoder<W>),
+    Zstd(zstd::Encoder<'static, W>),
+}
+
+impl<W: Write> Encoder<W> {
+    pub fn new(output: W, compression: Option<Compression>) -> io::Result<Self> {
+        Ok(match compression {
+            Some(Compression::Gzip) => {
+                Encoder::Gzip(GzEncoder::new(output, flate2::Compression::default()))
+            }
+            Some(Compression::Zstd) => Encoder::Zstd(zstd::Encoder::new(output, 0)?),
+            None => Encoder::Plain(output),
+        })
+    }
+
+    /// Writes out any trailing compressed data, returning the underlying
+    /// writer
+    pub fn finish(self) -> io::Result<W> {
+        match self {
+            Encoder::Plain(mut w) => {
+                w.flush()?;
+                Ok(w)
+            }
+            Encoder::Gzip(e) => e.finish(),
+            Encoder::Zstd(e) => e.finish(),
+        }
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Encoder::Plain(w) => w.write(buf),
+            Encoder::Gzip(e) => e.write(buf),
+            Encoder::Zstd(e) => e.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Encoder::Plain(w) => w.flush(),
+            Encoder::Gzip(e) => e.flush(),
+            Encoder::Zstd(e) => e.flush(),
+        }
+    }
+}
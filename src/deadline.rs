@@ -0,0 +1,264 @@
+use crate::{Authority, ClientId, DisputeError, DisputeTransaction, DisputeTransactionType, TxId};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::{collections::VecDeque, io, str::FromStr, time::Duration};
+
+/// Age after which an open dispute is settled automatically
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Deadline {
+    /// Number of transactions ledgered since the dispute was opened
+    Transactions(u64),
+    /// Time elapsed since the dispute was opened, as measured by transaction
+    /// timestamps
+    ///
+    /// Disputes opened before any timestamp was seen have no age in time
+    /// and are left open.
+    Duration(Duration),
+}
+
+impl FromStr for Deadline {
+    type Err = String;
+
+    /// Parses a deadline of either `<n>tx` transactions or `<n>s` seconds
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid dispute deadline: {}, expected <n>tx or <n>s", s);
+
+        if let Some(n) = s.strip_suffix("tx") {
+            n.parse().map(Deadline::Transactions).map_err(|_| invalid())
+        } else if let Some(n) = s.strip_suffix('s') {
+            n.parse()
+                .map(|secs| Deadline::Duration(Duration::from_secs(secs)))
+                .map_err(|_| invalid())
+        } else {
+            Err(invalid())
+        }
+    }
+}
+
+/// How disputes past their [Deadline] are settled
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum DeadlinePolicy {
+    /// Resolved on behalf of the disputing client
+    #[default]
+    Resolve,
+    /// Charged back on behalf of the disputed transaction's client
+    ///
+    /// Disputes which cannot be charged back, such as on locked accounts,
+    /// are resolved instead.
+    Chargeback,
+}
+
+impl FromStr for DeadlinePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "resolve" => Ok(DeadlinePolicy::Resolve),
+            "chargeback" => Ok(DeadlinePolicy::Chargeback),
+            _ => Err(format!("Unknown deadline policy: {}", s)),
+        }
+    }
+}
+
+/// Dispute awaiting its deadline
+struct Pending {
+    tx: TxId,
+    seq: u64,
+    opened_at: Option<u64>,
+}
+
+/// Tracks open disputes, in the order they were opened, until they are past
+/// their deadline
+pub(crate) struct Deadlines {
+    deadline: Deadline,
+    policy: DeadlinePolicy,
+    queue: VecDeque<Pending>,
+}
+
+impl Deadlines {
+    pub(crate) fn new(deadline: Deadline, policy: DeadlinePolicy) -> Self {
+        Self {
+            deadline,
+            policy,
+            queue: VecDeque::new(),
+        }
+    }
+
+    /// Tracks a newly opened dispute
+    pub(crate) fn track(&mut self, d: &DisputeTransaction) {
+        self.queue.push_back(Pending {
+            tx: d.tx(),
+            seq: d.opened_seq(),
+            opened_at: d.opened_at(),
+        });
+    }
+
+    /// Number of tracked disputes, as saved by a batch
+    pub(crate) fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Untracks disputes opened since the batch saving `len`
+    pub(crate) fn truncate(&mut self, len: usize) {
+        self.queue.truncate(len);
+    }
+
+    /// Takes the earliest opened dispute past its deadline, returning its tx
+    /// and the sequence number it was opened at
+    ///
+    /// The dispute may since have been closed.
+    fn pop_expired(&mut self, seq: u64, clock: Option<u64>) -> Option<(TxId, u64)> {
+        while let Some(pending) = self.queue.front() {
+            let expired = match self.deadline {
+                Deadline::Transactions(n) => seq - pending.seq >= n,
+                Deadline::Duration(duration) => match (pending.opened_at, clock) {
+                    (Some(opened_at), Some(now)) => {
+                        now.saturating_sub(opened_at) > duration.as_secs()
+                    }
+                    // Never expires
+                    (None, _) => {
+                        self.queue.pop_front();
+                        continue;
+                    }
+                    (Some(_), None) => false,
+                },
+            };
+
+            if !expired {
+                return None;
+            }
+
+            let pending = self.queue.pop_front().unwrap();
+            return Some((pending.tx, pending.seq));
+        }
+
+        None
+    }
+}
+
+/// Row of the dispute aging report
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct DisputeAge {
+    tx: TxId,
+    /// Client who opened the dispute
+    client: ClientId,
+    amount: Decimal,
+    /// Transactions ledgered since the dispute was opened
+    transactions: u64,
+    /// Seconds elapsed since the dispute was opened, where known
+    seconds: Option<u64>,
+}
+
+impl DisputeAge {
+    pub fn tx(&self) -> TxId {
+        self.tx
+    }
+
+    pub fn client(&self) -> ClientId {
+        self.client
+    }
+
+    pub fn amount(&self) -> Decimal {
+        self.amount
+    }
+
+    pub fn transactions(&self) -> u64 {
+        self.transactions
+    }
+
+    pub fn seconds(&self) -> Option<u64> {
+        self.seconds
+    }
+}
+
+impl Authority {
+    /// Lists every open dispute along with its age, oldest first
+    pub fn dispute_aging(&self) -> io::Result<Vec<DisputeAge>> {
+        let seq = self.compactor.seq();
+
+        let mut aging = vec![];
+        for tx in self.dispute_ledger.keys() {
+            let Some(d) = self.dispute_ledger.get(tx)? else {
+                continue;
+            };
+
+            aging.push((
+                d.opened_seq(),
+                DisputeAge {
+                    tx: d.tx(),
+                    client: d.client(),
+                    amount: d.amount().unwrap_or_default(),
+                    transactions: seq - d.opened_seq(),
+                    seconds: d
+                        .opened_at()
+                        .zip(self.clock)
+                        .map(|(opened_at, now)| now.saturating_sub(opened_at)),
+                },
+            ));
+        }
+
+        aging.sort_by_key(|(opened_seq, age)| (*opened_seq, age.tx));
+        Ok(aging.into_iter().map(|(_, age)| age).collect())
+    }
+
+    /// Settles every open dispute past its deadline according to the
+    /// deadline policy
+    ///
+    /// Settlements are recorded in the dispute history as though issued at
+    /// the latest timestamp seen.
+    pub(crate) fn settle_expired(&mut self) -> io::Result<()> {
+        loop {
+            let seq = self.compactor.seq();
+            let clock = self.clock;
+            let Some(deadlines) = &mut self.deadlines else {
+                return Ok(());
+            };
+            let policy = deadlines.policy;
+            let Some((tx, opened_seq)) = deadlines.pop_expired(seq, clock) else {
+                return Ok(());
+            };
+
+            // Skip disputes closed, and possibly reopened, since
+            let dispute = match self.dispute_ledger.get(&tx)? {
+                Some(d) if d.opened_seq() == opened_seq => d,
+                _ => continue,
+            };
+            // Escrows are charged back by their payer
+            let owner = match self.transaction_ledger.get(&tx)? {
+                Some(disputed) => disputed.client(),
+                None => match self.escrows.get(&tx) {
+                    Some(escrow) => escrow.payer(),
+                    None => continue,
+                },
+            };
+
+            let settlement = |kind, client| {
+                let t = DisputeTransaction::new(kind, client, tx);
+                match clock {
+                    Some(now) => t.with_timestamp(now),
+                    None => t,
+                }
+            };
+            let resolve = settlement(DisputeTransactionType::Resolve, dispute.client());
+
+            let res = match policy {
+                DeadlinePolicy::Resolve => Err(None),
+                DeadlinePolicy::Chargeback => self
+                    .apply_dispute(settlement(DisputeTransactionType::Chargeback, owner))
+                    .map_err(Some),
+            };
+
+            // The issuer of an open dispute may always resolve it, leaving
+            // only storage errors
+            match res {
+                Ok(()) => {}
+                Err(Some(DisputeError::Storage(e))) => return Err(e),
+                Err(_) => {
+                    if let Err(DisputeError::Storage(e)) = self.apply_dispute(resolve) {
+                        return Err(e);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,306 @@
+use crate::{
+    lifecycle, limits, Authority, ClientId, DisputeError, DisputeTransaction,
+    DisputeTransactionType, EscrowError, EscrowTransaction, EscrowTransactionType, OperationError,
+    OperationTransaction, OperationTransactionType, TxId, WalletId,
+};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::fmt;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EscrowStatus {
+    /// Funds are locked, awaiting release or refund
+    Open,
+    Released,
+    Refunded,
+}
+
+impl fmt::Display for EscrowStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EscrowStatus::Open => write!(f, "open"),
+            EscrowStatus::Released => write!(f, "released"),
+            EscrowStatus::Refunded => write!(f, "refunded"),
+        }
+    }
+}
+
+/// Funds locked by a payer for a payee, and their outcome
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Escrow {
+    tx: TxId,
+    payer: ClientId,
+    payee: ClientId,
+    /// Wallet of the payer the funds were locked from, and are refunded to
+    wallet: WalletId,
+    amount: Decimal,
+    status: EscrowStatus,
+    /// Latest timestamp seen when the escrow was opened
+    opened_at: Option<u64>,
+    /// Latest timestamp seen when the escrow was released or refunded
+    settled_at: Option<u64>,
+}
+
+impl Escrow {
+    pub fn tx(&self) -> TxId {
+        self.tx
+    }
+
+    pub fn payer(&self) -> ClientId {
+        self.payer
+    }
+
+    pub fn payee(&self) -> ClientId {
+        self.payee
+    }
+
+    pub fn wallet(&self) -> WalletId {
+        self.wallet
+    }
+
+    pub fn amount(&self) -> Decimal {
+        self.amount
+    }
+
+    pub fn status(&self) -> EscrowStatus {
+        self.status
+    }
+
+    pub fn opened_at(&self) -> Option<u64> {
+        self.opened_at
+    }
+
+    pub fn settled_at(&self) -> Option<u64> {
+        self.settled_at
+    }
+}
+
+impl Authority {
+    /// Applies escrow, release and refund transactions
+    pub(crate) fn apply_escrow(&mut self, t: EscrowTransaction) -> Result<(), EscrowError> {
+        if t.transaction_type() != EscrowTransactionType::Escrow {
+            let escrow = self.open_escrow(t.tx())?;
+
+            // Payers confirm releases, whereas payees agree to refunds
+            let (issuer, status) = match t.transaction_type() {
+                EscrowTransactionType::Release => (escrow.payer, EscrowStatus::Released),
+                _ => (escrow.payee, EscrowStatus::Refunded),
+            };
+            if t.client() != issuer {
+                return Err(EscrowError::Conflict(
+                    t.tx(),
+                    t.client(),
+                    t.transaction_type(),
+                ));
+            }
+            if self.dispute_ledger.get(&t.tx())?.is_some() {
+                return Err(EscrowError::Disputed(t.tx()));
+            }
+
+            // Locked parties may neither issue nor receive releases and
+            // refunds, leaving the escrow to be charged back
+            for party in [escrow.payer, escrow.payee] {
+                match self.client_state.get(&party) {
+                    Some(c) if c.locked() => return Err(OperationError::Locked(party).into()),
+                    Some(c) if c.closed() => return Err(OperationError::Closed(party).into()),
+                    _ => {}
+                }
+            }
+
+            self.settle_escrow(t.tx(), status);
+            return Ok(());
+        }
+
+        if self.tx_taken(t.tx()) || self.transaction_ledger.get(&t.tx())?.is_some() {
+            return Err(EscrowError::TransactionExists(t.tx()));
+        }
+        let (Some(amount), Some(payee)) = (t.amount(), t.counterparty()) else {
+            return Err(EscrowError::Invalid(t.tx()));
+        };
+        if amount <= Decimal::ZERO || payee == t.client() {
+            return Err(EscrowError::Invalid(t.tx()));
+        }
+
+        // Payees which don't exist yet are created on release, unless
+        // accounts must be opened first
+        match self.client_state.get(&payee) {
+            Some(c) if c.closed() => return Err(OperationError::Closed(payee).into()),
+            None if self.strict_accounts => {
+                return Err(OperationError::UnknownClient(t.tx(), payee).into())
+            }
+            _ => {}
+        }
+        // Escrowed funds leave the payer as though withdrawn, and so count
+        // against its withdrawal limits
+        let withdrawal = OperationTransaction::new(
+            OperationTransactionType::Withdrawal,
+            t.client(),
+            t.tx(),
+            amount,
+        )
+        .with_wallet(t.wallet());
+        let day = t.timestamp().or(self.clock).map_or(0, limits::day_of);
+        self.limits
+            .check(&withdrawal, day)
+            .map_err(|l| OperationError::LimitExceeded(t.tx(), l.clone()))?;
+
+        let Some(payer) = self.client_state.get_mut(&t.client()) else {
+            return Err(OperationError::UnknownClient(t.tx(), t.client()).into());
+        };
+
+        payer.apply_escrow(t.tx(), t.wallet(), amount)?;
+        self.limits.record(&withdrawal, day);
+        self.journal
+            .escrow(t.transaction_type(), t.tx(), t.client(), amount);
+        self.escrows.insert(
+            t.tx(),
+            Escrow {
+                tx: t.tx(),
+                payer: t.client(),
+                payee,
+                wallet: t.wallet(),
+                amount,
+                status: EscrowStatus::Open,
+                opened_at: self.clock,
+                settled_at: None,
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Escrow `tx`, provided it is still open
+    fn open_escrow(&self, tx: TxId) -> Result<Escrow, EscrowError> {
+        match self.escrows.get(&tx) {
+            Some(escrow) if escrow.status == EscrowStatus::Open => Ok(escrow.clone()),
+            Some(_) => Err(EscrowError::Settled(tx)),
+            None => Err(EscrowError::EscrowDoesntExist(tx)),
+        }
+    }
+
+    /// Credits the funds of open escrow `tx` to the payee when released, or
+    /// back to the payer when refunded
+    fn settle_escrow(&mut self, tx: TxId, status: EscrowStatus) {
+        // Escrow was checked to be open by the caller
+        let escrow = self.escrows.get_mut(&tx).unwrap();
+        escrow.status = status;
+        escrow.settled_at = self.clock;
+        let (kind, client, wallet) = match status {
+            EscrowStatus::Released => (EscrowTransactionType::Release, escrow.payee, 0),
+            _ => (EscrowTransactionType::Refund, escrow.payer, escrow.wallet),
+        };
+        let amount = escrow.amount;
+
+        // Neither party may close their account while the escrow is open
+        let kyc = self.kyc.as_ref();
+        let recipient = self
+            .client_state
+            .entry(client)
+            .or_insert_with(|| lifecycle::new_client(client, kyc));
+        let available = recipient
+            .wallet(wallet)
+            .map_or(Decimal::ZERO, |w| *w.available());
+        recipient.apply_escrow_credit(wallet, amount);
+        self.journal.escrow(kind, tx, client, amount);
+
+        // Releases are paid to the payee as though deposited
+        self.collect(
+            client,
+            wallet,
+            tx,
+            available,
+            kind == EscrowTransactionType::Release,
+        );
+    }
+
+    /// Applies dispute transactions on escrows
+    ///
+    /// Either party may dispute an open escrow, which may then be neither
+    /// released nor refunded until resolved by the disputing party, or
+    /// charged back by the payer which refunds the escrow. Escrows are
+    /// disputed in full and may not be reversed.
+    pub(crate) fn apply_escrow_dispute(
+        &mut self,
+        t: DisputeTransaction,
+    ) -> Result<(), DisputeError> {
+        let escrow = match self.open_escrow(t.tx()) {
+            Ok(escrow) => escrow,
+            Err(_) => return Err(DisputeError::EscrowSettled(t.tx())),
+        };
+        if t.amount().is_some_and(|amount| amount != escrow.amount) {
+            return Err(DisputeError::InvalidAmount(t.tx(), t.amount().unwrap()));
+        }
+
+        let existing_dispute = self.dispute_ledger.get(&t.tx())?;
+        match t.transaction_type() {
+            DisputeTransactionType::Dispute => {
+                if existing_dispute.is_some() {
+                    return Err(DisputeError::DisputeExists(t.tx()));
+                }
+                if t.client() != escrow.payer && t.client() != escrow.payee {
+                    return Err(DisputeError::EscrowConflict(
+                        t.tx(),
+                        t.client(),
+                        t.transaction_type(),
+                    ));
+                }
+
+                let mut opened = t.clone().with_amount(escrow.amount);
+                opened.set_opened(self.compactor.seq(), self.clock);
+                if let Some(deadlines) = &mut self.deadlines {
+                    deadlines.track(&opened);
+                }
+                self.dispute_ledger.insert(t.tx(), opened)?;
+            }
+            DisputeTransactionType::Resolve => {
+                let Some(existing_dispute) = existing_dispute else {
+                    return Err(DisputeError::DisputeDoesntExists(t.tx()));
+                };
+                if t.client() != existing_dispute.client() {
+                    return Err(DisputeError::DisputeConflict(t.tx(), t.client()));
+                }
+
+                self.dispute_ledger.remove(&t.tx())?;
+            }
+            DisputeTransactionType::Chargeback => {
+                if existing_dispute.is_none() {
+                    return Err(DisputeError::DisputeDoesntExists(t.tx()));
+                }
+                if t.client() != escrow.payer {
+                    return Err(DisputeError::ChargebackConflict(
+                        t.tx(),
+                        t.client(),
+                        escrow.payer,
+                    ));
+                }
+
+                self.dispute_ledger.remove(&t.tx())?;
+                self.settle_escrow(t.tx(), EscrowStatus::Refunded);
+            }
+            DisputeTransactionType::Reversal => {
+                return Err(DisputeError::EscrowConflict(
+                    t.tx(),
+                    t.client(),
+                    t.transaction_type(),
+                ))
+            }
+        }
+
+        self.dispute_history.push(t.with_amount(escrow.amount));
+        Ok(())
+    }
+
+    /// Open escrow `client` is party to, if any
+    pub(crate) fn escrow_of(&self, client: ClientId) -> Option<TxId> {
+        self.escrows
+            .values()
+            .find(|e| e.status == EscrowStatus::Open && (e.payer == client || e.payee == client))
+            .map(|e| e.tx)
+    }
+
+    /// Reports every escrow, whether open or settled, in order of tx
+    pub fn escrows(&self) -> Vec<Escrow> {
+        self.escrows.values().cloned().collect()
+    }
+}
@@ -0,0 +1,428 @@
+use crate::{
+    Authority, Client, ClientId, DisputeTransactionType, EscrowTransactionType,
+    OperationTransaction, OperationTransactionType, TxId,
+};
+use rust_decimal::Decimal;
+use serde::{Serialize, Serializer};
+use std::{collections::BTreeMap, fmt};
+
+/// Ledger account entries are posted between
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Account {
+    /// Funds a client may withdraw
+    ClientAvailable(ClientId),
+    /// Client funds held under dispute
+    ClientHeld(ClientId),
+    /// Debt owed by a client in collections
+    ClientDebt(ClientId),
+    /// Funds moved in and out through the payment network
+    Settlement,
+    /// Withdrawals re-credited to clients through disputes and chargebacks
+    ChargebackLoss,
+    /// Funds locked in escrow between clients
+    Escrow,
+}
+
+impl fmt::Display for Account {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Account::ClientAvailable(client) => write!(f, "client:{}:available", client),
+            Account::ClientHeld(client) => write!(f, "client:{}:held", client),
+            Account::ClientDebt(client) => write!(f, "client:{}:debt", client),
+            Account::Settlement => write!(f, "settlement"),
+            Account::ChargebackLoss => write!(f, "chargeback_loss"),
+            Account::Escrow => write!(f, "escrow"),
+        }
+    }
+}
+
+impl Serialize for Account {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+/// Balanced entry moving `amount` from the `debit` to the `credit` account
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct JournalEntry {
+    tx: TxId,
+    event: &'static str,
+    debit: Account,
+    credit: Account,
+    amount: Decimal,
+}
+
+impl JournalEntry {
+    pub fn tx(&self) -> TxId {
+        self.tx
+    }
+
+    /// Transaction type which caused the entry
+    pub fn event(&self) -> &'static str {
+        self.event
+    }
+
+    pub fn debit(&self) -> Account {
+        self.debit
+    }
+
+    pub fn credit(&self) -> Account {
+        self.credit
+    }
+
+    pub fn amount(&self) -> Decimal {
+        self.amount
+    }
+}
+
+/// Row of a [TrialBalance]
+///
+/// `balance` is the account's credit balance, which for client accounts
+/// matches the corresponding [Client](crate::Client) balance.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct AccountBalance {
+    account: Account,
+    debit: Decimal,
+    credit: Decimal,
+    balance: Decimal,
+}
+
+impl AccountBalance {
+    fn new(account: Account) -> Self {
+        Self {
+            account,
+            debit: Decimal::new(0, 4),
+            credit: Decimal::new(0, 4),
+            balance: Decimal::new(0, 4),
+        }
+    }
+
+    pub fn account(&self) -> Account {
+        self.account
+    }
+
+    pub fn debit(&self) -> Decimal {
+        self.debit
+    }
+
+    pub fn credit(&self) -> Decimal {
+        self.credit
+    }
+
+    pub fn balance(&self) -> Decimal {
+        self.balance
+    }
+}
+
+/// Client account of the [Journal] whose balance disagrees with that of the
+/// [Client]
+///
+/// Debt accounts are debited, such that their balance is expected to be the
+/// negated debt of the client.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Discrepancy {
+    account: Account,
+    balance: Decimal,
+    expected: Decimal,
+}
+
+impl Discrepancy {
+    pub fn account(&self) -> Account {
+        self.account
+    }
+
+    /// Balance of the account as posted to the journal
+    pub fn balance(&self) -> Decimal {
+        self.balance
+    }
+
+    /// Balance of the account according to the client
+    pub fn expected(&self) -> Decimal {
+        self.expected
+    }
+}
+
+impl fmt::Display for Discrepancy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Account {} balance: {} doesn't equal client balance: {}",
+            self.account, self.balance, self.expected
+        )
+    }
+}
+
+/// Debit and credit totals of every account in the [Journal], reconciled
+/// against client balances
+pub struct TrialBalance {
+    accounts: Vec<AccountBalance>,
+    debit: Decimal,
+    credit: Decimal,
+    discrepancies: Vec<Discrepancy>,
+}
+
+impl TrialBalance {
+    pub fn accounts(&self) -> &[AccountBalance] {
+        &self.accounts
+    }
+
+    pub fn debit(&self) -> Decimal {
+        self.debit
+    }
+
+    pub fn credit(&self) -> Decimal {
+        self.credit
+    }
+
+    /// Client accounts disagreeing with client balances
+    pub fn discrepancies(&self) -> &[Discrepancy] {
+        &self.discrepancies
+    }
+
+    /// Whether total debits equal total credits, and every client account
+    /// agrees with the client's balances
+    pub fn is_balanced(&self) -> bool {
+        self.debit == self.credit && self.discrepancies.is_empty()
+    }
+}
+
+/// Double-entry journal of every change to client balances
+///
+/// Account totals are always maintained, whereas individual entries are only
+/// retained when enabled, until drained.
+#[derive(Default)]
+pub struct Journal {
+    balances: BTreeMap<Account, AccountBalance>,
+    entries: Option<Vec<JournalEntry>>,
+}
+
+impl Journal {
+    pub(crate) fn retain_entries(&mut self) {
+        self.entries.get_or_insert_with(Vec::new);
+    }
+
+    /// Drains retained entries, in the order they were posted
+    pub fn drain(&mut self) -> impl Iterator<Item = JournalEntry> + '_ {
+        self.entries
+            .iter_mut()
+            .flat_map(|entries| entries.drain(..))
+    }
+
+    /// Totals of `account`, as saved by a batch
+    pub(crate) fn account(&self, account: Account) -> Option<AccountBalance> {
+        self.balances.get(&account).cloned()
+    }
+
+    /// Number of retained entries, as saved by a batch
+    pub(crate) fn entries_len(&self) -> usize {
+        self.entries.as_ref().map_or(0, Vec::len)
+    }
+
+    /// Restores the totals of an account previously saved by a batch
+    pub(crate) fn restore(&mut self, account: Account, balance: Option<AccountBalance>) {
+        match balance {
+            Some(balance) => self.balances.insert(account, balance),
+            None => self.balances.remove(&account),
+        };
+    }
+
+    /// Discards entries retained after the first `len`
+    pub(crate) fn truncate(&mut self, len: usize) {
+        if let Some(entries) = &mut self.entries {
+            entries.truncate(len);
+        }
+    }
+
+    pub fn balance(&self, account: Account) -> Decimal {
+        self.balances
+            .get(&account)
+            .map_or(Decimal::new(0, 4), |b| b.balance)
+    }
+
+    /// Totals every account, reconciling the available, held and debt
+    /// accounts of each of `clients` against its balances
+    fn trial_balance<'a, I>(&self, clients: I) -> TrialBalance
+    where
+        I: IntoIterator<Item = &'a Client>,
+    {
+        let accounts = self.balances.values().cloned().collect::<Vec<_>>();
+        let debit = accounts.iter().map(|a| a.debit).sum();
+        let credit = accounts.iter().map(|a| a.credit).sum();
+
+        let mut discrepancies = vec![];
+        for client in clients {
+            for (account, expected) in [
+                (Account::ClientAvailable(client.id()), *client.available()),
+                (Account::ClientHeld(client.id()), *client.held()),
+                (Account::ClientDebt(client.id()), -*client.debt()),
+            ] {
+                let balance = self.balance(account);
+                if balance != expected {
+                    discrepancies.push(Discrepancy {
+                        account,
+                        balance,
+                        expected,
+                    });
+                }
+            }
+        }
+
+        TrialBalance {
+            accounts,
+            debit,
+            credit,
+            discrepancies,
+        }
+    }
+
+    fn post(
+        &mut self,
+        tx: TxId,
+        event: &'static str,
+        debit: Account,
+        credit: Account,
+        amount: Decimal,
+    ) {
+        let d = self
+            .balances
+            .entry(debit)
+            .or_insert_with(|| AccountBalance::new(debit));
+        d.debit += amount;
+        d.balance -= amount;
+
+        let c = self
+            .balances
+            .entry(credit)
+            .or_insert_with(|| AccountBalance::new(credit));
+        c.credit += amount;
+        c.balance += amount;
+
+        if let Some(entries) = &mut self.entries {
+            entries.push(JournalEntry {
+                tx,
+                event,
+                debit,
+                credit,
+                amount,
+            });
+        }
+    }
+
+    /// Posts an applied deposit or withdrawal
+    pub(crate) fn operation(&mut self, t: &OperationTransaction) {
+        let available = Account::ClientAvailable(t.client());
+        match t.transaction_type() {
+            OperationTransactionType::Deposit => self.post(
+                t.tx(),
+                "deposit",
+                Account::Settlement,
+                available,
+                t.amount(),
+            ),
+            OperationTransactionType::Withdrawal => self.post(
+                t.tx(),
+                "withdrawal",
+                available,
+                Account::Settlement,
+                t.amount(),
+            ),
+        }
+    }
+
+    /// Posts `amount` of a negative available balance moved into debt
+    pub(crate) fn debt(&mut self, tx: TxId, client: ClientId, amount: Decimal) {
+        self.post(
+            tx,
+            "collection",
+            Account::ClientDebt(client),
+            Account::ClientAvailable(client),
+            amount,
+        );
+    }
+
+    /// Posts `amount` of debt repaid out of the available balance
+    pub(crate) fn repayment(&mut self, tx: TxId, client: ClientId, amount: Decimal) {
+        self.post(
+            tx,
+            "repayment",
+            Account::ClientAvailable(client),
+            Account::ClientDebt(client),
+            amount,
+        );
+    }
+
+    /// Posts `amount` locked in escrow by `client`, or released or refunded
+    /// to `client` out of escrow
+    pub(crate) fn escrow(
+        &mut self,
+        kind: EscrowTransactionType,
+        tx: TxId,
+        client: ClientId,
+        amount: Decimal,
+    ) {
+        let available = Account::ClientAvailable(client);
+        match kind {
+            EscrowTransactionType::Escrow => {
+                self.post(tx, "escrow", available, Account::Escrow, amount)
+            }
+            EscrowTransactionType::Release => {
+                self.post(tx, "release", Account::Escrow, available, amount)
+            }
+            EscrowTransactionType::Refund => {
+                self.post(tx, "refund", Account::Escrow, available, amount)
+            }
+        }
+    }
+
+    /// Posts an applied dispute transaction on `amount` of the disputed
+    /// transaction `t`
+    pub(crate) fn dispute(
+        &mut self,
+        kind: DisputeTransactionType,
+        t: &OperationTransaction,
+        amount: Decimal,
+    ) {
+        let available = Account::ClientAvailable(t.client());
+        let held = Account::ClientHeld(t.client());
+
+        let (event, debit, credit) = match (kind, t.transaction_type()) {
+            (DisputeTransactionType::Dispute, OperationTransactionType::Deposit) => {
+                ("dispute", available, held)
+            }
+            (DisputeTransactionType::Dispute, OperationTransactionType::Withdrawal) => {
+                ("dispute", Account::ChargebackLoss, held)
+            }
+            (DisputeTransactionType::Resolve, OperationTransactionType::Deposit) => {
+                ("resolve", held, available)
+            }
+            (DisputeTransactionType::Resolve, OperationTransactionType::Withdrawal) => {
+                ("resolve", held, Account::ChargebackLoss)
+            }
+            (DisputeTransactionType::Chargeback, OperationTransactionType::Deposit) => {
+                ("chargeback", held, Account::Settlement)
+            }
+            (DisputeTransactionType::Chargeback, OperationTransactionType::Withdrawal) => {
+                ("chargeback", held, available)
+            }
+            (DisputeTransactionType::Reversal, OperationTransactionType::Deposit) => {
+                ("reversal", available, Account::Settlement)
+            }
+            (DisputeTransactionType::Reversal, OperationTransactionType::Withdrawal) => {
+                ("reversal", Account::Settlement, available)
+            }
+        };
+
+        self.post(t.tx(), event, debit, credit, amount);
+    }
+}
+
+impl Authority {
+    /// Trial balance of the journal, reconciled against the balances of every
+    /// client
+    pub fn trial_balance(&self) -> TrialBalance {
+        self.journal.trial_balance(self.client_state.values())
+    }
+}
@@ -0,0 +1,92 @@
+use crate::ClientId;
+use csv::Reader;
+use serde::Deserialize;
+use std::{collections::HashMap, fmt, io::Read, str::FromStr};
+
+#[derive(thiserror::Error, Debug)]
+pub enum KycError {
+    #[error(transparent)]
+    Csv(#[from] csv::Error),
+    #[error("Invalid KYC status: {0}")]
+    Invalid(String),
+}
+
+/// Know your customer verification status of a client
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum KycStatus {
+    /// Not yet verified
+    #[default]
+    Pending,
+    Verified,
+    Rejected,
+}
+
+impl fmt::Display for KycStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KycStatus::Pending => write!(f, "pending"),
+            KycStatus::Verified => write!(f, "verified"),
+            KycStatus::Rejected => write!(f, "rejected"),
+        }
+    }
+}
+
+impl FromStr for KycStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(KycStatus::Pending),
+            "verified" => Ok(KycStatus::Verified),
+            "rejected" => Ok(KycStatus::Rejected),
+            _ => Err(s.to_string()),
+        }
+    }
+}
+
+/// Raw status row as found in a KYC file
+///
+/// ```csv
+/// client,status
+/// 1,verified
+/// 2,rejected
+/// ```
+#[derive(Deserialize)]
+struct RawKyc {
+    client: ClientId,
+    status: String,
+}
+
+/// Registry of the KYC status of each client, where clients missing from the
+/// registry are pending verification
+#[derive(Clone, Debug, Default)]
+pub struct KycRegistry {
+    statuses: HashMap<ClientId, KycStatus>,
+}
+
+impl KycRegistry {
+    /// Reads a registry from a KYC file
+    pub fn from_reader<T>(rdr: Reader<T>) -> Result<Self, KycError>
+    where
+        T: Read,
+    {
+        let statuses = rdr
+            .into_deserialize::<RawKyc>()
+            .map(|r| {
+                let r = r?;
+                let status = r.status.parse().map_err(KycError::Invalid)?;
+                Ok((r.client, status))
+            })
+            .collect::<Result<_, KycError>>()?;
+
+        Ok(Self { statuses })
+    }
+
+    pub fn insert(&mut self, client: ClientId, status: KycStatus) {
+        self.statuses.insert(client, status);
+    }
+
+    pub fn status(&self, client: ClientId) -> KycStatus {
+        self.statuses.get(&client).copied().unwrap_or_default()
+    }
+}
@@ -1,116 +1,508 @@
+pub use audit::{verify_audit, AuditError, AuditLog};
+pub use batch::BatchError;
 pub use client::Client;
+use collections::Collections;
+pub use collections::{Collection, CollectionPolicy};
+use compaction::Compactor;
+pub use compression::{decompress, Compression, Encoder};
+use deadline::Deadlines;
+pub use deadline::{Deadline, DeadlinePolicy, DisputeAge};
+pub use escrow::{Escrow, EscrowStatus};
+pub use journal::{Account, AccountBalance, Discrepancy, Journal, JournalEntry, TrialBalance};
+pub use kyc::{KycError, KycRegistry, KycStatus};
+pub use limits::{Limit, LimitError, Limits, Window};
+pub use mapping::{detect_delimiter, Column, ColumnMapping, Field, MappingError};
+pub use merge::{Merge, MergeOrder, Provenance};
+pub use merkle::{client_row, MerkleProof, MerkleTree, Side};
+pub use reasons::ReasonSummary;
 use rust_decimal::Decimal;
 use serde::Serialize;
-use std::collections::{btree_map::Values, hash_map::Entry, BTreeMap, HashMap};
+pub use signature::{
+    read_signing_keys, sign, KeyError, KeyRegistry, SignatureError, SigningKey, VerifyingKey,
+};
+pub use sqlite::ExportError;
+use std::{
+    collections::{btree_map::Values, BTreeMap},
+    io,
+    slice::Iter,
+    time::Duration,
+};
+pub use storage::{FileStorage, MemoryStorage, Record, Storage};
 pub use transaction::{
-    DisputeTransaction, DisputeTransactionType, OperationTransaction, OperationTransactionType,
-    Transaction,
+    AccountTransaction, AccountTransactionType, ClientId, DisputeTransaction,
+    DisputeTransactionType, EscrowTransaction, EscrowTransactionType, OperationTransaction,
+    OperationTransactionType, Transaction, TransferTransaction, TxId, WalletId,
 };
-pub use transcode::transcode;
+pub use transcode::{transcode, transcode_lines, transcode_mapped, try_transcode, TranscodeError};
+pub use verify::Violation;
+pub use wallet::Wallet;
 
+mod audit;
+mod batch;
 mod client;
+mod collections;
+mod compaction;
+mod compression;
+mod deadline;
+mod escrow;
+mod journal;
+mod kyc;
+mod lifecycle;
+mod limits;
+mod mapping;
+mod merge;
+mod merkle;
+mod reasons;
+mod signature;
+mod sqlite;
+mod storage;
 #[cfg(test)]
 mod tests;
 mod transaction;
 mod transcode;
+mod verify;
+mod wallet;
 
 #[derive(thiserror::Error, Debug)]
 pub enum OperationError {
     #[error("Transaction with tx: {0} already exists")]
-    TransactionExists(u32),
+    TransactionExists(TxId),
+    #[error("Transaction with tx: {0} conflicts with existing transaction with the same tx")]
+    TransactionConflict(TxId),
     #[error("Transaction with tx: {0} withdraw: {1} exceeded available units: {2}")]
-    WithdrawExceeded(u32, Decimal, Decimal),
+    WithdrawExceeded(TxId, Decimal, Decimal),
     #[error("Account {0} locked")]
-    Locked(u16),
+    Locked(ClientId),
+    #[error("Transaction with tx: {0} breached limit: {1}")]
+    LimitExceeded(TxId, Limit),
+    #[error("Account {0} closed")]
+    Closed(ClientId),
+    #[error("Transaction with tx: {0} for unknown client: {1}")]
+    UnknownClient(TxId, ClientId),
+    #[error("Transaction with tx: {0} withdrawal by unverified client: {1}")]
+    Unverified(TxId, ClientId),
+    #[error("Transfer with tx: {0} must move a positive amount between distinct wallets")]
+    InvalidTransfer(TxId),
+    #[error(transparent)]
+    Storage(#[from] io::Error),
 }
 
 #[derive(thiserror::Error, Debug)]
 pub enum DisputeError {
     #[error("Dispute with tx: {0} already exists")]
-    DisputeExists(u32),
+    DisputeExists(TxId),
     #[error("Dispute with tx: {0} doesn't exist")]
-    DisputeDoesntExists(u32),
+    DisputeDoesntExists(TxId),
     #[error("Transaction with tx: {0} doesn't exist")]
-    TransactionDoesntExists(u32),
+    TransactionDoesntExists(TxId),
     #[error("Cannot resolve dispute with tx: {0} client: {1} didn't issue themselves")]
-    DisputeConflict(u32, u16),
+    DisputeConflict(TxId, ClientId),
     #[error("Cannot issue chargeback with tx: {0} by client: {1} to {2}")]
-    ChargebackConflict(u32, u16, u16),
+    ChargebackConflict(TxId, ClientId, ClientId),
     #[error("Account {0} locked")]
-    Locked(u16),
+    Locked(ClientId),
+    #[error("Dispute window for tx: {0} has expired")]
+    WindowExpired(TxId),
+    #[error("Transaction with tx: {0} has been reversed")]
+    Reversed(TxId),
+    #[error("Cannot reverse transaction with tx: {0} by client: {1} to {2}")]
+    ReversalConflict(TxId, ClientId, ClientId),
+    #[error("Invalid amount: {1} for dispute of tx: {0}")]
+    InvalidAmount(TxId, Decimal),
+    #[error("Amount: {1} for dispute of tx: {0} exceeds remaining amount: {2}")]
+    AmountExceeded(TxId, Decimal, Decimal),
+    #[error("Account {0} closed")]
+    Closed(ClientId),
+    #[error("Escrow with tx: {0} already settled")]
+    EscrowSettled(TxId),
+    #[error("Cannot {2} escrow with tx: {0} by client: {1}")]
+    EscrowConflict(TxId, ClientId, DisputeTransactionType),
+    #[error(transparent)]
+    Storage(#[from] io::Error),
 }
 
-#[derive(Default, Serialize)]
+#[derive(thiserror::Error, Debug)]
+pub enum AccountError {
+    #[error("Transaction with tx: {0} already exists")]
+    TransactionExists(TxId),
+    #[error("Transaction with tx: {0} conflicts with existing transaction with the same tx")]
+    TransactionConflict(TxId),
+    #[error("Account {0} is already open")]
+    AlreadyOpen(ClientId),
+    #[error("Account {0} doesn't exist")]
+    AccountDoesntExist(ClientId),
+    #[error("Account {0} already closed")]
+    Closed(ClientId),
+    #[error("Account {0} locked")]
+    Locked(ClientId),
+    #[error("Cannot close account {0} with non-zero balance")]
+    Balance(ClientId),
+    #[error("Cannot close account {0} with open dispute on tx: {1}")]
+    Disputed(ClientId, TxId),
+    #[error("Cannot close account {0} with open escrow tx: {1}")]
+    Escrowed(ClientId, TxId),
+    #[error(transparent)]
+    Storage(#[from] io::Error),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum EscrowError {
+    #[error("Transaction with tx: {0} already exists")]
+    TransactionExists(TxId),
+    #[error("Escrow with tx: {0} doesn't exist")]
+    EscrowDoesntExist(TxId),
+    #[error("Escrow with tx: {0} must lock a positive amount for another client")]
+    Invalid(TxId),
+    #[error("Escrow with tx: {0} already settled")]
+    Settled(TxId),
+    #[error("Escrow with tx: {0} is disputed")]
+    Disputed(TxId),
+    #[error("Cannot {2} escrow with tx: {0} by client: {1}")]
+    Conflict(TxId, ClientId, EscrowTransactionType),
+    #[error(transparent)]
+    Operation(#[from] OperationError),
+    #[error(transparent)]
+    Storage(#[from] io::Error),
+}
+
+/// Outcome of successfully applying a transaction
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Applied,
+    /// Exact resubmission of an already applied transaction, which is ignored
+    Duplicate,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum TransactionError {
+    #[error(transparent)]
+    Operation(#[from] OperationError),
+    #[error(transparent)]
+    Dispute(#[from] DisputeError),
+    #[error(transparent)]
+    Account(#[from] AccountError),
+    #[error(transparent)]
+    Escrow(#[from] EscrowError),
+    #[error(transparent)]
+    Signature(#[from] SignatureError),
+    #[error(transparent)]
+    Storage(#[from] io::Error),
+}
+
+#[derive(Serialize)]
 #[serde(transparent)]
 pub struct Authority {
-    client_state: BTreeMap<u16, Client>,
+    client_state: BTreeMap<ClientId, Client>,
+    #[serde(skip)]
+    transaction_ledger: Box<dyn Storage<TxId, OperationTransaction>>,
     #[serde(skip)]
-    transaction_ledger: HashMap<u32, OperationTransaction>,
+    dispute_ledger: Box<dyn Storage<TxId, DisputeTransaction>>,
+    /// Every accepted dispute, resolve and chargeback transaction
     #[serde(skip)]
-    dispute_ledger: HashMap<u32, DisputeTransaction>,
+    dispute_history: Vec<DisputeTransaction>,
+    #[serde(skip)]
+    limits: Limits,
+    /// Maximum age, in seconds, of a transaction which may still be disputed
+    #[serde(skip)]
+    dispute_window: Option<u64>,
+    /// Latest transaction timestamp seen
+    #[serde(skip)]
+    clock: Option<u64>,
+    #[serde(skip)]
+    compactor: Compactor,
+    #[serde(skip)]
+    journal: Journal,
+    #[serde(skip)]
+    keys: Option<KeyRegistry>,
+    #[serde(skip)]
+    deadlines: Option<Deadlines>,
+    #[serde(skip)]
+    collections: Option<Collections>,
+    #[serde(skip)]
+    kyc: Option<KycRegistry>,
+    /// Whether accounts must be opened before use
+    #[serde(skip)]
+    strict_accounts: bool,
+    /// Every escrow, whether open or settled
+    #[serde(skip)]
+    escrows: BTreeMap<TxId, Escrow>,
+    /// Every applied transfer, kept apart from the transaction ledger as
+    /// transfers may not be disputed
+    #[serde(skip)]
+    transfers: BTreeMap<TxId, TransferTransaction>,
+    /// Every applied account transaction
+    #[serde(skip)]
+    accounts: BTreeMap<TxId, AccountTransaction>,
+}
+
+impl Default for Authority {
+    fn default() -> Self {
+        Self {
+            client_state: BTreeMap::new(),
+            transaction_ledger: Box::<MemoryStorage<_, _>>::default(),
+            dispute_ledger: Box::<MemoryStorage<_, _>>::default(),
+            dispute_history: Vec::new(),
+            limits: Limits::default(),
+            dispute_window: None,
+            clock: None,
+            compactor: Compactor::default(),
+            journal: Journal::default(),
+            keys: None,
+            deadlines: None,
+            collections: None,
+            kyc: None,
+            strict_accounts: false,
+            escrows: BTreeMap::new(),
+            transfers: BTreeMap::new(),
+            accounts: BTreeMap::new(),
+        }
+    }
 }
 
 impl Authority {
+    /// Stores the transaction and dispute ledgers in the given backends
+    /// instead of in memory
+    ///
+    /// Backends are expected to be empty.
+    pub fn with_storage<T, D>(mut self, transaction_ledger: T, dispute_ledger: D) -> Self
+    where
+        T: Storage<TxId, OperationTransaction> + 'static,
+        D: Storage<TxId, DisputeTransaction> + 'static,
+    {
+        self.transaction_ledger = Box::new(transaction_ledger);
+        self.dispute_ledger = Box::new(dispute_ledger);
+        self
+    }
+
+    /// Retains every [JournalEntry] posted until drained through
+    /// [journal_mut](Authority::journal_mut)
+    pub fn with_journal(mut self) -> Self {
+        self.journal.retain_entries();
+        self
+    }
+
+    /// Requires every transaction to be signed by its issuing client's key in
+    /// `keys`
+    pub fn with_key_registry(mut self, keys: KeyRegistry) -> Self {
+        self.keys = Some(keys);
+        self
+    }
+
+    /// Enforces the given withdrawal [Limits] on all subsequent operations
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Rejects disputes on transactions older than `window`
+    ///
+    /// A transaction's age is measured up to the dispute's own timestamp or,
+    /// if absent, the latest timestamp seen. Transactions without a timestamp
+    /// may always be disputed.
+    pub fn with_dispute_window(mut self, window: Duration) -> Self {
+        self.dispute_window = Some(window.as_secs());
+        self
+    }
+
+    /// Rejects disputes on transactions after `max_age` further transactions
+    /// have been ledgered
+    pub fn with_max_tx_age(mut self, max_age: u64) -> Self {
+        self.compactor.set_max_age(max_age);
+        self
+    }
+
+    /// Settles open disputes once past `deadline`, resolving or charging
+    /// them back according to `policy`
+    pub fn with_dispute_deadline(mut self, deadline: Deadline, policy: DeadlinePolicy) -> Self {
+        self.deadlines = Some(Deadlines::new(deadline, policy));
+        self
+    }
+
+    /// Moves negative available balances into collections, such that later
+    /// deposits repay the debt according to `policy`
+    pub fn with_collections(mut self, policy: CollectionPolicy) -> Self {
+        self.collections = Some(Collections::new(policy));
+        self
+    }
+
+    /// Tracks the KYC status of clients, such that only verified clients may
+    /// withdraw
+    pub fn with_kyc(mut self, registry: KycRegistry) -> Self {
+        self.track_kyc(registry);
+        self
+    }
+
+    /// Rejects operations for clients whose account was never opened
+    pub fn with_strict_accounts(mut self) -> Self {
+        self.strict_accounts = true;
+        self
+    }
+}
+
+impl Authority {
+    /// Whether `tx` was evicted from the transaction ledger, or is taken by a
+    /// transaction kept apart from it
+    fn tx_taken(&self, tx: TxId) -> bool {
+        self.compactor.evicted(tx)
+            || self.escrows.contains_key(&tx)
+            || self.transfers.contains_key(&tx)
+            || self.accounts.contains_key(&tx)
+    }
+
     /// Applies unit withdraw and deposit operations
-    fn apply_operation(&mut self, t: OperationTransaction) -> Result<(), OperationError> {
-        match self.transaction_ledger.entry(t.tx()) {
-            // Ensure transaction doesn't exist already
-            Entry::Occupied(_) => return Err(OperationError::TransactionExists(t.tx())),
-            Entry::Vacant(v) => {
-                let client = self
-                    .client_state
-                    .entry(t.client())
-                    .or_insert_with(|| Client::new(t.client()));
-
-                client.apply_operation_transaction(&t)?;
-                // If apply_operation_transaction succeeds only then we can ledge transaction
-                v.insert(t);
-            }
+    fn apply_operation(&mut self, t: OperationTransaction) -> Result<Outcome, OperationError> {
+        // Evicted transactions can no longer be compared against, so even
+        // exact resubmissions of them are rejected rather than ignored
+        if self.tx_taken(t.tx()) {
+            return Err(OperationError::TransactionExists(t.tx()));
         }
 
-        Ok(())
+        // Exact resubmissions are idempotent, whereas any other reuse of a tx
+        // is a conflict
+        if let Some(existing) = self.transaction_ledger.get(&t.tx())? {
+            let duplicate = existing.transaction_type() == t.transaction_type()
+                && existing.client() == t.client()
+                && existing.wallet() == t.wallet()
+                && existing.amount() == t.amount();
+
+            return match duplicate {
+                true => Ok(Outcome::Duplicate),
+                false => Err(OperationError::TransactionConflict(t.tx())),
+            };
+        }
+
+        // Transactions without a timestamp fall on the day of the latest
+        // timestamp seen, such that replaying input is deterministic
+        let day = t.timestamp().or(self.clock).map_or(0, limits::day_of);
+        self.limits
+            .check(&t, day)
+            .map_err(|l| OperationError::LimitExceeded(t.tx(), l.clone()))?;
+
+        if self.strict_accounts && !self.client_state.contains_key(&t.client()) {
+            return Err(OperationError::UnknownClient(t.tx(), t.client()));
+        }
+
+        let kyc = self.kyc.as_ref();
+        let client = self
+            .client_state
+            .entry(t.client())
+            .or_insert_with(|| lifecycle::new_client(t.client(), kyc));
+
+        let available = client
+            .wallet(t.wallet())
+            .map_or(Decimal::ZERO, |w| *w.available());
+        client.apply_operation_transaction(&t)?;
+        self.journal.operation(&t);
+        self.limits.record(&t, day);
+        self.compactor.track(&t, self.dispute_window);
+        self.collect(
+            t.client(),
+            t.wallet(),
+            t.tx(),
+            available,
+            t.transaction_type() == OperationTransactionType::Deposit,
+        );
+        // If apply_operation_transaction succeeds only then we can ledge transaction
+        self.transaction_ledger.insert(t.tx(), t)?;
+
+        Ok(Outcome::Applied)
     }
 
     /// Applies dispute operations
     fn apply_dispute(&mut self, t: DisputeTransaction) -> Result<(), DisputeError> {
+        if self.escrows.contains_key(&t.tx()) {
+            return self.apply_escrow_dispute(t);
+        }
+
         // All dispute transactions refer to a transaction
-        let disputed_transaction = self
-            .transaction_ledger
-            .get(&t.tx())
-            .ok_or_else(|| DisputeError::TransactionDoesntExists(t.tx()))?;
+        let disputed_transaction = match self.transaction_ledger.get(&t.tx())? {
+            Some(disputed_transaction) => disputed_transaction,
+            // Evicted transactions are past their dispute window
+            None if self.compactor.evicted(t.tx()) => {
+                return Err(DisputeError::WindowExpired(t.tx()))
+            }
+            None => return Err(DisputeError::TransactionDoesntExists(t.tx())),
+        };
+
+        // Reversed transactions have been undone and can no longer be acted on
+        if disputed_transaction.reversed() {
+            return Err(DisputeError::Reversed(t.tx()));
+        }
 
         // Transaction exists therefore client must also exist in our state
-        // as client_state and transaction_state are insert only.
+        // as client_state is insert only.
         let client = self
             .client_state
             .get_mut(&disputed_transaction.client())
             .unwrap();
+        let wallet = disputed_transaction.wallet();
+        let owner = client.id();
+        let available = client
+            .wallet(wallet)
+            .map_or(Decimal::ZERO, |w| *w.available());
 
-        let entry = self.dispute_ledger.entry(t.tx());
-        match t.transaction_type() {
-            DisputeTransactionType::Dispute => match entry {
-                Entry::Occupied(_) => return Err(DisputeError::DisputeExists(t.tx())),
-                Entry::Vacant(v) => {
-                    client.apply_dispute(disputed_transaction)?;
-                    v.insert(t);
+        // Amount held, released, charged back or reversed
+        let existing_dispute = self.dispute_ledger.get(&t.tx())?;
+        let amount = match t.transaction_type() {
+            DisputeTransactionType::Dispute => match existing_dispute {
+                Some(_) => return Err(DisputeError::DisputeExists(t.tx())),
+                None => {
+                    let now = t.timestamp().or(self.clock);
+                    if let (Some(window), Some(issued), Some(now)) =
+                        (self.dispute_window, disputed_transaction.timestamp(), now)
+                    {
+                        if now.saturating_sub(issued) > window {
+                            return Err(DisputeError::WindowExpired(t.tx()));
+                        }
+                    }
+
+                    // Partial disputes hold part of the amount, leaving the
+                    // remainder to be disputed later on
+                    let disputable = disputed_transaction.disputable();
+                    let amount = t.amount().unwrap_or(disputable);
+                    if amount <= Decimal::ZERO {
+                        return Err(DisputeError::InvalidAmount(t.tx(), amount));
+                    }
+                    if amount > disputable {
+                        return Err(DisputeError::AmountExceeded(t.tx(), amount, disputable));
+                    }
+
+                    client.apply_dispute(&disputed_transaction, amount)?;
+                    self.journal
+                        .dispute(t.transaction_type(), &disputed_transaction, amount);
+                    let mut opened = t.clone().with_amount(amount);
+                    opened.set_opened(self.compactor.seq(), self.clock);
+                    if let Some(deadlines) = &mut self.deadlines {
+                        deadlines.track(&opened);
+                    }
+                    self.dispute_ledger.insert(t.tx(), opened)?;
+                    amount
                 }
             },
-            DisputeTransactionType::Resolve => match entry {
-                Entry::Occupied(o) => {
-                    let existing_dispute = o.get();
-
+            DisputeTransactionType::Resolve => match existing_dispute {
+                Some(existing_dispute) => {
                     // Client may only resolve disputes they issued themselves
                     if t.client() != existing_dispute.client() {
                         return Err(DisputeError::DisputeConflict(t.tx(), t.client()));
                     }
 
-                    client.apply_resolve(disputed_transaction)?;
-                    o.remove_entry();
+                    // Resolves release the dispute in full
+                    let held = existing_dispute
+                        .amount()
+                        .unwrap_or(disputed_transaction.amount());
+                    if t.amount().is_some_and(|amount| amount != held) {
+                        return Err(DisputeError::InvalidAmount(t.tx(), t.amount().unwrap()));
+                    }
+                    client.apply_resolve(&disputed_transaction, held)?;
+                    self.journal
+                        .dispute(t.transaction_type(), &disputed_transaction, held);
+                    self.dispute_ledger.remove(&t.tx())?;
+                    held
                 }
-                Entry::Vacant(_) => return Err(DisputeError::DisputeDoesntExists(t.tx())),
+                None => return Err(DisputeError::DisputeDoesntExists(t.tx())),
             },
-            DisputeTransactionType::Chargeback => match entry {
-                Entry::Occupied(o) => {
+            DisputeTransactionType::Chargeback => match existing_dispute {
+                Some(existing_dispute) => {
                     // Can only issue chargeback on transactions from own account
                     if t.client() != disputed_transaction.client() {
                         return Err(DisputeError::ChargebackConflict(
@@ -120,18 +512,136 @@ impl Authority {
                         ));
                     }
 
-                    client.apply_chargeback(disputed_transaction)?;
-                    o.remove_entry();
+                    let held = existing_dispute
+                        .amount()
+                        .unwrap_or(disputed_transaction.amount());
+                    let amount = t.amount().unwrap_or(held);
+                    if amount <= Decimal::ZERO {
+                        return Err(DisputeError::InvalidAmount(t.tx(), amount));
+                    }
+                    if amount > held {
+                        return Err(DisputeError::AmountExceeded(t.tx(), amount, held));
+                    }
+
+                    client.apply_chargeback(&disputed_transaction, amount)?;
+                    self.journal
+                        .dispute(t.transaction_type(), &disputed_transaction, amount);
+
+                    // Whatever part of the dispute isn't charged back is
+                    // released as though resolved
+                    if amount < held {
+                        client.apply_resolve(&disputed_transaction, held - amount)?;
+                        self.journal.dispute(
+                            DisputeTransactionType::Resolve,
+                            &disputed_transaction,
+                            held - amount,
+                        );
+                    }
+                    self.dispute_ledger.remove(&t.tx())?;
+
+                    let mut charged_back = disputed_transaction;
+                    charged_back.add_charged_back(amount);
+                    self.transaction_ledger.insert(t.tx(), charged_back)?;
+                    amount
                 }
-                Entry::Vacant(_) => return Err(DisputeError::DisputeDoesntExists(t.tx())),
+                None => return Err(DisputeError::DisputeDoesntExists(t.tx())),
             },
-        }
+            DisputeTransactionType::Reversal => match existing_dispute {
+                // Disputed transactions must first be resolved
+                Some(_) => return Err(DisputeError::DisputeExists(t.tx())),
+                None => {
+                    // Can only reverse transactions on own account
+                    if t.client() != disputed_transaction.client() {
+                        return Err(DisputeError::ReversalConflict(
+                            t.tx(),
+                            t.client(),
+                            disputed_transaction.client(),
+                        ));
+                    }
+
+                    // Only what hasn't been charged back is left to reverse
+                    let amount = disputed_transaction.disputable();
+                    if amount <= Decimal::ZERO {
+                        return Err(DisputeError::InvalidAmount(t.tx(), amount));
+                    }
+                    if t.amount().is_some_and(|a| a != amount) {
+                        return Err(DisputeError::InvalidAmount(t.tx(), t.amount().unwrap()));
+                    }
+
+                    client.apply_reversal(&disputed_transaction, amount)?;
+                    self.journal
+                        .dispute(t.transaction_type(), &disputed_transaction, amount);
+
+                    let mut reversed = disputed_transaction;
+                    reversed.set_reversed();
+                    self.transaction_ledger.insert(t.tx(), reversed)?;
+                    amount
+                }
+            },
+        };
+
+        self.collect(owner, wallet, t.tx(), available, false);
 
+        // History records the amount actually acted on, even where omitted
+        self.dispute_history.push(t.with_amount(amount));
         Ok(())
     }
+
+    /// Evicts transactions which can no longer be disputed from the
+    /// transaction ledger
+    fn compact(&mut self) -> io::Result<()> {
+        let transaction_ledger = &mut self.transaction_ledger;
+        let dispute_ledger = &self.dispute_ledger;
+
+        let mut res = Ok(());
+        self.compactor.compact(
+            self.dispute_window,
+            self.clock,
+            |tx| dispute_ledger.contains(&tx),
+            |tx| {
+                if let Err(e) = transaction_ledger.remove(&tx) {
+                    res = Err(e);
+                }
+            },
+        );
+        res
+    }
+
+    /// Applies a single transaction, leaving compaction to the caller
+    fn apply_transaction(&mut self, t: Transaction) -> Result<Outcome, TransactionError> {
+        if let Some(keys) = &self.keys {
+            keys.verify(&t)?;
+        }
+
+        if let Some(timestamp) = t.timestamp() {
+            self.clock = self.clock.max(Some(timestamp));
+        }
+
+        match t {
+            Transaction::Operation(o) => self.apply_operation(o).map_err(Into::into),
+            Transaction::Dispute(d) => self
+                .apply_dispute(d)
+                .map(|_| Outcome::Applied)
+                .map_err(Into::into),
+            Transaction::Account(a) => self.apply_account(a).map_err(Into::into),
+            Transaction::Transfer(t) => self.apply_transfer(t).map_err(Into::into),
+            Transaction::Escrow(e) => self
+                .apply_escrow(e)
+                .map(|_| Outcome::Applied)
+                .map_err(Into::into),
+        }
+    }
 }
 
 impl Authority {
+    /// Applies a single transaction to the [Authority]
+    pub fn apply(&mut self, t: Transaction) -> Result<Outcome, TransactionError> {
+        let res = self.apply_transaction(t);
+        self.settle_expired()?;
+        self.compact()?;
+        res
+    }
+
     /// Allows applying an iterator of transactions to the [Authority]
     ///
     /// In a multi-input environment such as where multiple clients connect to
@@ -142,25 +652,31 @@ impl Authority {
         I: Iterator<Item = Transaction>,
     {
         for t in iter {
-            match t {
-                Transaction::Operation(o) => {
-                    if let Err(e) = self.apply_operation(o) {
-                        eprintln!("{}", e);
-                    }
-                }
-                Transaction::Dispute(d) => {
-                    if let Err(e) = self.apply_dispute(d) {
-                        eprintln!("{}", e);
-                    }
-                }
+            if let Err(e) = self.apply(t) {
+                eprintln!("{}", e);
             }
         }
     }
 
     /// Iterator across client state
-    pub fn iter_clients(&mut self) -> Values<'_, u16, Client> {
+    pub fn iter_clients(&mut self) -> Values<'_, ClientId, Client> {
         self.client_state.values()
     }
+
+    /// Double-entry journal of all applied transactions
+    pub fn journal(&self) -> &Journal {
+        &self.journal
+    }
+
+    pub fn journal_mut(&mut self) -> &mut Journal {
+        &mut self.journal
+    }
+
+    /// Iterator across accepted dispute transactions, in the order they were
+    /// applied
+    pub fn iter_dispute_history(&self) -> Iter<'_, DisputeTransaction> {
+        self.dispute_history.iter()
+    }
 }
 
 impl FromIterator<Transaction> for Authority {
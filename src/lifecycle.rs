@@ -0,0 +1,109 @@
+use crate::{
+    AccountError, AccountTransaction, AccountTransactionType, Authority, Client, ClientId,
+    KycRegistry, KycStatus, Outcome,
+};
+
+/// Creates the account of `client`, tracking its KYC status where a registry
+/// is in use
+pub(crate) fn new_client(client: ClientId, kyc: Option<&KycRegistry>) -> Client {
+    let mut c = Client::new(client);
+    if let Some(kyc) = kyc {
+        c.set_kyc(kyc.status(client));
+    }
+    c
+}
+
+impl Authority {
+    /// Applies account open and close transactions
+    ///
+    /// Closed accounts may be reopened, whereas only accounts holding no
+    /// funds, owing nothing and party to no open dispute may be closed.
+    pub(crate) fn apply_account(&mut self, t: AccountTransaction) -> Result<Outcome, AccountError> {
+        // Exact resubmissions are idempotent, whereas any other reuse of a tx
+        // is a conflict
+        if let Some(existing) = self.accounts.get(&t.tx()) {
+            let duplicate = existing.transaction_type() == t.transaction_type()
+                && existing.client() == t.client();
+
+            return match duplicate {
+                true => Ok(Outcome::Duplicate),
+                false => Err(AccountError::TransactionConflict(t.tx())),
+            };
+        }
+        if self.tx_taken(t.tx()) || self.transaction_ledger.get(&t.tx())?.is_some() {
+            return Err(AccountError::TransactionExists(t.tx()));
+        }
+
+        match t.transaction_type() {
+            AccountTransactionType::Open => match self.client_state.get_mut(&t.client()) {
+                Some(client) if client.closed() => client.set_closed(false),
+                Some(_) => return Err(AccountError::AlreadyOpen(t.client())),
+                None => {
+                    let client = new_client(t.client(), self.kyc.as_ref());
+                    self.client_state.insert(t.client(), client);
+                }
+            },
+            AccountTransactionType::Close => {
+                let Some(client) = self.client_state.get(&t.client()) else {
+                    return Err(AccountError::AccountDoesntExist(t.client()));
+                };
+                if client.closed() {
+                    return Err(AccountError::Closed(t.client()));
+                }
+                if client.locked() {
+                    return Err(AccountError::Locked(t.client()));
+                }
+                if !client.is_empty() {
+                    return Err(AccountError::Balance(t.client()));
+                }
+
+                if let Some(tx) = self.escrow_of(t.client()) {
+                    return Err(AccountError::Escrowed(t.client(), tx));
+                }
+
+                // Disputes opened by the client, or on its transactions
+                for tx in self.dispute_ledger.keys() {
+                    let Some(d) = self.dispute_ledger.get(tx)? else {
+                        continue;
+                    };
+                    let owner = self.transaction_ledger.get(tx)?.map(|o| o.client());
+                    if d.client() == t.client() || owner == Some(t.client()) {
+                        return Err(AccountError::Disputed(t.client(), *tx));
+                    }
+                }
+
+                // Client was fetched above
+                self.client_state
+                    .get_mut(&t.client())
+                    .unwrap()
+                    .set_closed(true);
+            }
+        }
+
+        self.accounts.insert(t.tx(), t);
+        Ok(Outcome::Applied)
+    }
+
+    /// Records the KYC status of `client`, tracking the status of every
+    /// client from now on if not already
+    pub fn set_kyc(&mut self, client: ClientId, status: KycStatus) {
+        if self.kyc.is_none() {
+            self.track_kyc(KycRegistry::default());
+        }
+        if let Some(kyc) = &mut self.kyc {
+            kyc.insert(client, status);
+        }
+        if let Some(c) = self.client_state.get_mut(&client) {
+            c.set_kyc(status);
+        }
+    }
+
+    /// Tracks the KYC status of clients from `registry`, including that of
+    /// clients created before
+    pub(crate) fn track_kyc(&mut self, registry: KycRegistry) {
+        for client in self.client_state.values_mut() {
+            client.set_kyc(registry.status(client.id()));
+        }
+        self.kyc = Some(registry);
+    }
+}
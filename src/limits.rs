@@ -0,0 +1,297 @@
+use crate::{ClientId, OperationTransaction, OperationTransactionType};
+use csv::Reader;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    io::Read,
+};
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+#[derive(thiserror::Error, Debug)]
+pub enum LimitError {
+    #[error(transparent)]
+    Csv(#[from] csv::Error),
+    #[error("Invalid limit: {0}")]
+    Invalid(String),
+}
+
+/// Span over which withdrawals are accumulated against a [Limit]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Window {
+    /// The client's last `n` operation transactions, including the one being
+    /// applied
+    Transactions(u64),
+    /// The UTC calendar day of the transaction's timestamp, or of the latest
+    /// timestamp seen for transactions without one
+    Day,
+}
+
+impl fmt::Display for Window {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Window::Transactions(n) => write!(f, "{} transactions", n),
+            Window::Day => write!(f, "day"),
+        }
+    }
+}
+
+/// Cap on the number and/or sum of withdrawals a client may make within a
+/// [Window]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Limit {
+    client: Option<ClientId>,
+    window: Window,
+    max_count: Option<u32>,
+    max_amount: Option<Decimal>,
+}
+
+impl Limit {
+    /// Creates a limit for `client`, or a default limit for every client
+    /// without limits of their own when `client` is `None`
+    pub fn new(
+        client: Option<ClientId>,
+        window: Window,
+        max_count: Option<u32>,
+        max_amount: Option<Decimal>,
+    ) -> Self {
+        Self {
+            client,
+            window,
+            max_count,
+            max_amount,
+        }
+    }
+
+    pub fn client(&self) -> Option<ClientId> {
+        self.client
+    }
+
+    pub fn window(&self) -> Window {
+        self.window
+    }
+
+    pub fn max_count(&self) -> Option<u32> {
+        self.max_count
+    }
+
+    pub fn max_amount(&self) -> Option<Decimal> {
+        self.max_amount
+    }
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.client {
+            Some(client) => write!(f, "client {}", client)?,
+            None => write!(f, "default")?,
+        }
+        if let Some(count) = self.max_count {
+            write!(f, " max {} withdrawals", count)?;
+        }
+        if let Some(amount) = self.max_amount {
+            write!(f, " max {} withdrawn", amount)?;
+        }
+        write!(f, " per {}", self.window)
+    }
+}
+
+/// Raw limit row as found in a limits config file
+///
+/// ```csv
+/// client,window,max_count,max_amount
+/// ,day,5,
+/// 1,10,,100.0
+/// ```
+///
+/// An empty `client` denotes a default limit, and `window` is either `day` or
+/// a number of transactions.
+#[derive(Deserialize)]
+struct RawLimit {
+    client: Option<ClientId>,
+    window: String,
+    max_count: Option<u32>,
+    max_amount: Option<Decimal>,
+}
+
+impl TryFrom<RawLimit> for Limit {
+    type Error = String;
+
+    fn try_from(limit: RawLimit) -> Result<Self, Self::Error> {
+        let window = match limit.window.as_str() {
+            "day" => Window::Day,
+            n => match n.parse::<u64>() {
+                Ok(n) if n > 0 => Window::Transactions(n),
+                _ => return Err(format!("Unknown window: {}", limit.window)),
+            },
+        };
+
+        if limit.max_count.is_none() && limit.max_amount.is_none() {
+            return Err("Limit must set max_count or max_amount".to_string());
+        }
+
+        let max_amount = limit.max_amount.map(|mut amount| {
+            amount.rescale(4);
+            amount
+        });
+
+        Ok(Limit::new(
+            limit.client,
+            window,
+            limit.max_count,
+            max_amount,
+        ))
+    }
+}
+
+/// A withdrawal retained for as long as it may fall within some [Window]
+#[derive(Clone)]
+struct Withdrawal {
+    seq: u64,
+    day: u64,
+    amount: Decimal,
+}
+
+/// Per client record of recent operation transactions
+#[derive(Clone, Default)]
+pub(crate) struct Usage {
+    /// Number of operation transactions applied to the client
+    seq: u64,
+    withdrawals: VecDeque<Withdrawal>,
+}
+
+/// Set of configured [Limits](Limit) and the client usage they are checked
+/// against
+#[derive(Default)]
+pub struct Limits {
+    limits: Vec<Limit>,
+    usage: HashMap<ClientId, Usage>,
+}
+
+impl Limits {
+    pub fn new(limits: Vec<Limit>) -> Self {
+        Self {
+            limits,
+            usage: HashMap::new(),
+        }
+    }
+
+    /// Reads limits from a limits config file
+    pub fn from_reader<T>(rdr: Reader<T>) -> Result<Self, LimitError>
+    where
+        T: Read,
+    {
+        let limits = rdr
+            .into_deserialize::<RawLimit>()
+            .map(|r| Limit::try_from(r?).map_err(LimitError::Invalid))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self::new(limits))
+    }
+
+    /// Usage recorded against `client`, as saved by a batch
+    pub(crate) fn usage(&self, client: ClientId) -> Option<Usage> {
+        self.usage.get(&client).cloned()
+    }
+
+    /// Restores usage previously saved by a batch
+    pub(crate) fn restore_usage(&mut self, client: ClientId, usage: Option<Usage>) {
+        match usage {
+            Some(usage) => self.usage.insert(client, usage),
+            None => self.usage.remove(&client),
+        };
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.limits.is_empty()
+    }
+
+    /// Limits applicable to `client`, where client specific limits replace
+    /// the defaults
+    fn applicable(&self, client: ClientId) -> impl Iterator<Item = &Limit> {
+        let specific = self.limits.iter().any(|l| l.client == Some(client));
+        self.limits.iter().filter(move |l| {
+            if specific {
+                l.client == Some(client)
+            } else {
+                l.client.is_none()
+            }
+        })
+    }
+
+    /// Checks whether applying `t` on `day` would breach any limit, returning
+    /// the first limit breached
+    pub(crate) fn check(&self, t: &OperationTransaction, day: u64) -> Result<(), &Limit> {
+        if t.transaction_type() != OperationTransactionType::Withdrawal {
+            return Ok(());
+        }
+
+        let usage = self.usage.get(&t.client());
+        let seq = usage.map_or(0, |u| u.seq) + 1;
+
+        for limit in self.applicable(t.client()) {
+            let (count, amount) = usage
+                .into_iter()
+                .flat_map(|u| u.withdrawals.iter())
+                .filter(|w| match limit.window {
+                    Window::Transactions(n) => w.seq + n > seq,
+                    Window::Day => w.day == day,
+                })
+                .fold((1, t.amount()), |(count, amount), w| {
+                    (count + 1, amount + w.amount)
+                });
+
+            let count_exceeded = limit.max_count.is_some_and(|max| count > max);
+            let amount_exceeded = limit.max_amount.is_some_and(|max| amount > max);
+            if count_exceeded || amount_exceeded {
+                return Err(limit);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Records an applied operation transaction against its client's usage
+    pub(crate) fn record(&mut self, t: &OperationTransaction, day: u64) {
+        if self.limits.is_empty() {
+            return;
+        }
+
+        let horizon = self
+            .limits
+            .iter()
+            .filter_map(|l| match l.window {
+                Window::Transactions(n) => Some(n),
+                Window::Day => None,
+            })
+            .max()
+            .unwrap_or(0);
+        let daily = self.limits.iter().any(|l| l.window == Window::Day);
+
+        let usage = self.usage.entry(t.client()).or_default();
+        usage.seq += 1;
+
+        if t.transaction_type() == OperationTransactionType::Withdrawal {
+            usage.withdrawals.push_back(Withdrawal {
+                seq: usage.seq,
+                day,
+                amount: t.amount(),
+            });
+        }
+
+        // Drop withdrawals which can no longer fall within any window. Days
+        // are compared rather than relied upon to increase, as timestamps
+        // may go back in time.
+        let seq = usage.seq;
+        usage
+            .withdrawals
+            .retain(|w| w.seq + horizon > seq || (daily && w.day >= day));
+    }
+}
+
+/// UTC calendar day of `timestamp` as days since the unix epoch
+pub(crate) fn day_of(timestamp: u64) -> u64 {
+    timestamp / SECONDS_PER_DAY
+}
@@ -1,23 +1,455 @@
-use credit::{transcode, Authority};
+use credit::{
+    client_row, decompress, detect_delimiter, read_signing_keys, sign, transcode_lines,
+    transcode_mapped, try_transcode, verify_audit, AuditLog, Authority, CollectionPolicy,
+    ColumnMapping, Compression, Deadline, DeadlinePolicy, Encoder, FileStorage, KeyRegistry,
+    KycRegistry, Limits, Merge, MergeOrder, MerkleTree, Outcome, Transaction,
+};
 use csv::Writer;
-use std::env;
+use std::{
+    env,
+    error::Error,
+    fs::File,
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    process,
+    time::Duration,
+};
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let path = env::args()
-        .nth(1)
-        .ok_or("Expected path to input file as argument")?;
+/// Exit code when a row of input is rejected in strict mode
+const STRICT_EXIT_CODE: i32 = 2;
+
+/// Reports an input rejected in strict mode and exits
+fn reject(e: String) -> ! {
+    eprintln!("{}", e);
+    process::exit(STRICT_EXIT_CODE)
+}
+
+/// Value following `flag` on the command line
+fn value<I>(args: &mut I, flag: &str) -> Result<String, String>
+where
+    I: Iterator<Item = String>,
+{
+    args.next()
+        .ok_or_else(|| format!("Expected value after {}", flag))
+}
 
-    let rdr = csv::ReaderBuilder::new()
+/// Opens an input file of transactions, or stdin when `path` is `-`,
+/// decompressing it if need be and detecting its delimiter unless given
+fn open_input(
+    path: &str,
+    delimiter: Option<u8>,
+    has_headers: bool,
+) -> Result<csv::Reader<Box<dyn BufRead>>, Box<dyn Error>> {
+    let mut input = match path {
+        "-" => decompress(io::stdin().lock(), None)?,
+        _ => decompress(BufReader::new(File::open(path)?), Some(Path::new(path)))?,
+    };
+    let delimiter = match delimiter {
+        Some(delimiter) => delimiter,
+        None => detect_delimiter(input.fill_buf()?),
+    };
+
+    Ok(csv::ReaderBuilder::new()
         .flexible(true)
         .trim(csv::Trim::All)
-        .from_path(path)?;
+        .delimiter(delimiter)
+        .has_headers(has_headers)
+        .from_reader(input))
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args = env::args().skip(1).peekable();
+    match args.peek().map(String::as_str) {
+        Some("verify-audit") => {
+            args.next();
+            verify_audit_command(args)
+        }
+        Some("sign") => {
+            args.next();
+            sign_command(args)
+        }
+        Some("public-keys") => {
+            args.next();
+            public_keys_command(args)
+        }
+        _ => run(args),
+    }
+}
+
+/// Verifies the hash chain of an audit log, optionally against an expected
+/// head hash
+fn verify_audit_command<I>(mut args: I) -> Result<(), Box<dyn Error>>
+where
+    I: Iterator<Item = String>,
+{
+    let path = args
+        .next()
+        .ok_or("Expected path to audit log as argument")?;
+    let expected_head = args.next();
+
+    let rdr = csv::Reader::from_path(path)?;
+    let head = verify_audit(rdr, expected_head.as_deref())?;
+    println!("{}", head);
+
+    Ok(())
+}
+
+/// Signs every transaction of an input file with its client's signing key,
+/// outputting the signed transactions
+fn sign_command<I>(mut args: I) -> Result<(), Box<dyn Error>>
+where
+    I: Iterator<Item = String>,
+{
+    let keys = args
+        .next()
+        .ok_or("Expected path to signing keys as argument")?;
+    let path = args
+        .next()
+        .ok_or("Expected path to input file as argument")?;
+
+    let keys = read_signing_keys(csv::Reader::from_path(keys)?)?;
+    let rdr = open_input(&path, None, true)?;
+
+    let mut wtr = Writer::from_writer(std::io::stdout());
+    wtr.write_record(Transaction::FIELDS.iter().chain(&["signature"]))?;
+    for t in transcode_mapped(rdr, &ColumnMapping::default())? {
+        let key = keys
+            .get(&t.client())
+            .ok_or_else(|| format!("Missing signing key for client: {}", t.client()))?;
+        let t = sign(t, key);
 
-    let mut authority = Authority::from_iter(transcode(rdr));
+        let signature = hex::encode(t.signature().unwrap());
+        wtr.write_record(t.fields().iter().chain([&signature]))?;
+    }
+
+    Ok(())
+}
+
+/// Outputs the public key registry matching a file of signing keys
+fn public_keys_command<I>(mut args: I) -> Result<(), Box<dyn Error>>
+where
+    I: Iterator<Item = String>,
+{
+    let keys = args
+        .next()
+        .ok_or("Expected path to signing keys as argument")?;
+    let mut keys = read_signing_keys(csv::Reader::from_path(keys)?)?
+        .into_iter()
+        .collect::<Vec<_>>();
+    keys.sort_by_key(|(client, _)| *client);
 
     let mut wtr = Writer::from_writer(std::io::stdout());
-    for client in authority.iter_clients() {
-        wtr.serialize(client)?;
+    wtr.write_record(["client", "key"])?;
+    for (client, key) in keys {
+        wtr.serialize((client, hex::encode(key.verifying_key().as_bytes())))?;
+    }
+
+    Ok(())
+}
+
+/// Processes input files and outputs client state
+fn run<I>(mut args: I) -> Result<(), Box<dyn Error>>
+where
+    I: Iterator<Item = String>,
+{
+    let mut paths = Vec::new();
+    let mut limits = Limits::default();
+    let mut dispute_window = None;
+    let mut max_tx_age = None;
+    let mut dispute_deadline = None;
+    let mut deadline_policy = DeadlinePolicy::default();
+    let mut dispute_aging = None;
+    let mut collection_policy = None;
+    let mut collections = None;
+    let mut wallets = None;
+    let mut escrows = None;
+    let mut storage_dir = None;
+    let mut sqlite = None;
+    let mut journal = None;
+    let mut trial_balance = None;
+    let mut dispute_reasons = None;
+    let mut verify = false;
+    let mut audit = None;
+    let mut merkle = None;
+    let mut keys = None;
+    let mut kyc = None;
+    let mut strict_accounts = false;
+    let mut mapping = ColumnMapping::default();
+    let mut has_headers = true;
+    let mut delimiter = None;
+    let mut output = None;
+    let mut compression = None;
+    let mut order = MergeOrder::default();
+    let mut strict = false;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--limits" => {
+                let rdr = csv::ReaderBuilder::new()
+                    .trim(csv::Trim::All)
+                    .from_path(value(&mut args, &arg)?)?;
+                limits = Limits::from_reader(rdr)?;
+            }
+            "--dispute-window" => {
+                let seconds = value(&mut args, &arg)?.parse()?;
+                dispute_window = Some(Duration::from_secs(seconds));
+            }
+            "--max-tx-age" => max_tx_age = Some(value(&mut args, &arg)?.parse()?),
+            "--dispute-deadline" => {
+                dispute_deadline = Some(value(&mut args, &arg)?.parse::<Deadline>()?)
+            }
+            "--deadline-policy" => deadline_policy = value(&mut args, &arg)?.parse()?,
+            "--collection-policy" => {
+                collection_policy = Some(value(&mut args, &arg)?.parse::<CollectionPolicy>()?)
+            }
+            "--collections" => collections = Some(Writer::from_path(value(&mut args, &arg)?)?),
+            "--escrows" => escrows = Some(Writer::from_path(value(&mut args, &arg)?)?),
+            "--wallets" => wallets = Some(Writer::from_path(value(&mut args, &arg)?)?),
+            "--dispute-aging" => dispute_aging = Some(Writer::from_path(value(&mut args, &arg)?)?),
+            "--storage-dir" => storage_dir = Some(PathBuf::from(value(&mut args, &arg)?)),
+            "--sqlite" => sqlite = Some(PathBuf::from(value(&mut args, &arg)?)),
+            "--journal" => journal = Some(Writer::from_path(value(&mut args, &arg)?)?),
+            "--trial-balance" => trial_balance = Some(Writer::from_path(value(&mut args, &arg)?)?),
+            "--dispute-reasons" => {
+                dispute_reasons = Some(Writer::from_path(value(&mut args, &arg)?)?)
+            }
+            "--verify" => verify = true,
+            "--audit" => audit = Some(AuditLog::new(File::create(value(&mut args, &arg)?)?)?),
+            "--merkle" => merkle = Some(Writer::from_path(value(&mut args, &arg)?)?),
+            "--keys" => {
+                let rdr = csv::Reader::from_path(value(&mut args, &arg)?)?;
+                keys = Some(KeyRegistry::from_reader(rdr)?);
+            }
+            "--kyc" => {
+                let rdr = csv::ReaderBuilder::new()
+                    .trim(csv::Trim::All)
+                    .from_path(value(&mut args, &arg)?)?;
+                kyc = Some(KycRegistry::from_reader(rdr)?);
+            }
+            "--strict-accounts" => strict_accounts = true,
+            "--columns" => {
+                let rdr = csv::ReaderBuilder::new()
+                    .trim(csv::Trim::All)
+                    .from_path(value(&mut args, &arg)?)?;
+                mapping = ColumnMapping::from_reader(rdr)?;
+            }
+            "--no-headers" => has_headers = false,
+            "--delimiter" => match value(&mut args, &arg)?.as_bytes() {
+                [byte] => delimiter = Some(*byte),
+                b"\\t" => delimiter = Some(b'\t'),
+                _ => return Err("Delimiter must be a single byte".into()),
+            },
+            "--output" => output = Some(PathBuf::from(value(&mut args, &arg)?)),
+            "--compress" => compression = Some(value(&mut args, &arg)?.parse::<Compression>()?),
+            "--merge" => order = value(&mut args, &arg)?.parse()?,
+            "--strict" => strict = true,
+            _ => paths.push(arg),
+        }
+    }
+
+    if paths.is_empty() {
+        return Err("Expected path to input file as argument".into());
+    }
+
+    // In strict mode every input is validated in full before any transaction
+    // is applied, such that a bad row leaves all state and output untouched.
+    // Files are read once to validate and again to apply rather than held in
+    // memory, whereas stdin can only be read once and so is buffered
+    let mut merge = Merge::new(order);
+    for path in &paths {
+        if strict {
+            let rdr = open_input(path, delimiter, has_headers)?;
+            let mut rows = match try_transcode(rdr, &mapping) {
+                Ok(rows) => rows,
+                Err(e) => reject(format!("{}: {}", path, e)),
+            };
+
+            if path == "-" {
+                match rows.collect::<Result<Vec<_>, _>>() {
+                    Ok(rows) => merge = merge.with_source(path, rows),
+                    Err(e) => reject(format!("{}: {}", path, e)),
+                }
+                continue;
+            }
+            if let Some(Err(e)) = rows.find(Result::is_err) {
+                reject(format!("{}: {}", path, e));
+            }
+        }
+
+        let rdr = open_input(path, delimiter, has_headers)?;
+        merge = merge.with_source(path, transcode_lines(rdr, &mapping, path)?);
+    }
+
+    let mut authority = Authority::default().with_limits(limits);
+    if let Some(window) = dispute_window {
+        authority = authority.with_dispute_window(window);
+    }
+    if let Some(age) = max_tx_age {
+        authority = authority.with_max_tx_age(age);
+    }
+    let collecting = collection_policy.is_some() || collections.is_some();
+    if collecting {
+        authority = authority.with_collections(collection_policy.unwrap_or_default());
+    }
+    if let Some(deadline) = dispute_deadline {
+        authority = authority.with_dispute_deadline(deadline, deadline_policy);
+    }
+    if let Some(kyc) = kyc {
+        authority = authority.with_kyc(kyc);
+    }
+    if strict_accounts {
+        authority = authority.with_strict_accounts();
+    }
+    if let Some(dir) = storage_dir {
+        authority = authority.with_storage(
+            FileStorage::create(dir.join("transactions.ledger"))?,
+            FileStorage::create(dir.join("disputes.ledger"))?,
+        );
+    }
+    if journal.is_some() {
+        authority = authority.with_journal();
+    }
+    if let Some(keys) = keys {
+        authority = authority.with_key_registry(keys);
+    }
+
+    let mut duplicates = 0;
+    for (provenance, t) in merge {
+        let res = match &mut audit {
+            Some(log) => {
+                let res = authority.apply(t.clone());
+                log.record(&t, &res)?;
+                res
+            }
+            None => authority.apply(t),
+        };
+
+        match res {
+            Ok(Outcome::Applied) => {}
+            Ok(Outcome::Duplicate) => duplicates += 1,
+            Err(e) => eprintln!("{}: {}", provenance, e),
+        }
+
+        if let Some(wtr) = &mut journal {
+            for entry in authority.journal_mut().drain() {
+                wtr.serialize(entry)?;
+            }
+        }
+    }
+
+    if duplicates > 0 {
+        eprintln!("Ignored {} duplicate transactions", duplicates);
+    }
+
+    if let Some(mut log) = audit {
+        log.flush()?;
+        eprintln!("Audit head: {}", log.head());
+    }
+
+    if let Some(mut wtr) = trial_balance {
+        let report = authority.trial_balance();
+        for account in report.accounts() {
+            wtr.serialize(account)?;
+        }
+        wtr.serialize(("total", report.debit(), report.credit(), ""))?;
+
+        if report.debit() != report.credit() {
+            eprintln!(
+                "Trial balance does not balance, debit: {} credit: {}",
+                report.debit(),
+                report.credit()
+            );
+        }
+        for discrepancy in report.discrepancies() {
+            eprintln!("{}", discrepancy);
+        }
+    }
+
+    if let Some(mut wtr) = dispute_reasons {
+        for summary in authority.dispute_reasons() {
+            wtr.serialize(summary)?;
+        }
+    }
+
+    if let Some(mut wtr) = wallets {
+        for wallet in authority.wallets() {
+            wtr.serialize(wallet)?;
+        }
+    }
+
+    if let Some(mut wtr) = escrows {
+        for escrow in authority.escrows() {
+            wtr.serialize(escrow)?;
+        }
+    }
+
+    if let Some(mut wtr) = collections {
+        for collection in authority.collections() {
+            wtr.serialize(collection)?;
+        }
+    }
+
+    if let Some(mut wtr) = dispute_aging {
+        for age in authority.dispute_aging()? {
+            wtr.serialize(age)?;
+        }
+    }
+
+    if let Some(db) = sqlite {
+        authority.export_sqlite(db)?;
+    }
+
+    if verify {
+        let violations = authority.verify()?;
+        for violation in &violations {
+            eprintln!("{}", violation);
+        }
+        if !violations.is_empty() {
+            return Err(format!("Verification found {} violations", violations.len()).into());
+        }
+    }
+
+    if let Some(mut wtr) = merkle {
+        let tree = MerkleTree::new(authority.iter_clients());
+
+        wtr.write_record(["client", "row", "proof"])?;
+        for client in authority.iter_clients() {
+            let proof = tree.proof(client.id()).unwrap();
+            wtr.serialize((client.id(), client_row(client), proof.to_string()))?;
+        }
+
+        eprintln!("Merkle root: {}", hex::encode(tree.root()));
+    }
+
+    let output: Box<dyn Write> = match output {
+        Some(path) => {
+            compression = compression.or_else(|| Compression::from_extension(&path));
+            Box::new(File::create(path)?)
+        }
+        None => Box::new(io::stdout()),
+    };
+
+    // Debt left out of available balances is reported alongside them, such
+    // that each row still adds up, only once collections are enabled
+    let mut wtr = Writer::from_writer(Encoder::new(output, compression)?);
+    if collecting {
+        wtr.write_record(["client", "available", "held", "total", "locked", "debt"])?;
+        for client in authority.iter_clients() {
+            wtr.write_record([
+                client.id().to_string(),
+                client.available().to_string(),
+                client.held().to_string(),
+                client.total().to_string(),
+                client.locked().to_string(),
+                client.debt().to_string(),
+            ])?;
+        }
+    } else {
+        for client in authority.iter_clients() {
+            wtr.serialize(client)?;
+        }
     }
+    wtr.into_inner().map_err(|e| e.into_error())?.finish()?;
 
     Ok(())
 }
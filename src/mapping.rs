@@ -0,0 +1,234 @@
+use csv::{Reader, StringRecord};
+use serde::Deserialize;
+use std::{collections::HashMap, fmt, io::Read, str::FromStr};
+
+#[derive(thiserror::Error, Debug)]
+pub enum MappingError {
+    #[error(transparent)]
+    Csv(#[from] csv::Error),
+    #[error("Invalid column mapping: {0}")]
+    Invalid(String),
+}
+
+/// Transaction field read from an input column
+///
+/// Fields are declared in the order of the canonical header, such that each
+/// field's discriminant is its index in [Field::ALL].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Field {
+    Type,
+    Client,
+    Tx,
+    Amount,
+    Timestamp,
+    Signature,
+    Reason,
+    Reference,
+    Wallet,
+    ToWallet,
+    Counterparty,
+}
+
+impl Field {
+    /// Every field, in the order of the canonical header
+    pub(crate) const ALL: [Field; 11] = [
+        Field::Type,
+        Field::Client,
+        Field::Tx,
+        Field::Amount,
+        Field::Timestamp,
+        Field::Signature,
+        Field::Reason,
+        Field::Reference,
+        Field::Wallet,
+        Field::ToWallet,
+        Field::Counterparty,
+    ];
+
+    fn required(self) -> bool {
+        matches!(self, Field::Type | Field::Client | Field::Tx)
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Field::Type => write!(f, "type"),
+            Field::Client => write!(f, "client"),
+            Field::Tx => write!(f, "tx"),
+            Field::Amount => write!(f, "amount"),
+            Field::Timestamp => write!(f, "timestamp"),
+            Field::Signature => write!(f, "signature"),
+            Field::Reason => write!(f, "reason"),
+            Field::Reference => write!(f, "reference"),
+            Field::Wallet => write!(f, "wallet"),
+            Field::ToWallet => write!(f, "to_wallet"),
+            Field::Counterparty => write!(f, "counterparty"),
+        }
+    }
+}
+
+impl FromStr for Field {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Field::ALL
+            .into_iter()
+            .find(|field| field.to_string() == s)
+            .ok_or_else(|| format!("Unknown field: {}", s))
+    }
+}
+
+/// Input column, either by header name or zero based index
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Column {
+    Name(String),
+    Index(usize),
+}
+
+impl FromStr for Column {
+    type Err = String;
+
+    /// Parses a column, where purely numeric columns are taken as indexes
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err("Empty column".to_string());
+        }
+
+        Ok(match s.parse() {
+            Ok(index) => Column::Index(index),
+            Err(_) => Column::Name(s.to_string()),
+        })
+    }
+}
+
+/// Raw mapping row as found in a column mapping config file
+///
+/// ```csv
+/// field,column
+/// type,kind
+/// client,account
+/// tx,ref
+/// amount,3
+/// ```
+#[derive(Deserialize)]
+struct RawMapping {
+    field: String,
+    column: String,
+}
+
+/// Mapping of input columns to transaction fields
+///
+/// Fields without a mapping are read from the column of the same name, if
+/// any, such that only the columns which differ need to be configured.
+#[derive(Clone, Debug, Default)]
+pub struct ColumnMapping {
+    columns: HashMap<Field, Column>,
+}
+
+impl ColumnMapping {
+    /// Reads the field of each column from a column mapping config file
+    pub fn from_reader<T>(rdr: Reader<T>) -> Result<Self, MappingError>
+    where
+        T: Read,
+    {
+        let mut mapping = Self::default();
+        for r in rdr.into_deserialize::<RawMapping>() {
+            let r = r?;
+            let field = r.field.parse().map_err(MappingError::Invalid)?;
+            let column = r.column.parse().map_err(MappingError::Invalid)?;
+            mapping = mapping.with_column(field, column);
+        }
+
+        Ok(mapping)
+    }
+
+    /// Reads `field` from `column`
+    pub fn with_column(mut self, field: Field, column: Column) -> Self {
+        self.columns.insert(field, column);
+        self
+    }
+
+    /// Resolves the index of each field's column, in the order of
+    /// [Field::ALL], against the input `headers`
+    pub(crate) fn resolve(
+        &self,
+        headers: &StringRecord,
+    ) -> Result<[Option<usize>; Field::ALL.len()], MappingError> {
+        let position = |name: &str| headers.iter().position(|header| header == name);
+
+        let mut indexes = [None; Field::ALL.len()];
+        for (index, field) in indexes.iter_mut().zip(Field::ALL) {
+            *index = match self.columns.get(&field) {
+                Some(Column::Index(i)) => Some(*i),
+                Some(Column::Name(name)) => Some(position(name).ok_or_else(|| {
+                    MappingError::Invalid(format!("Missing column {} for {}", name, field))
+                })?),
+                None if field == Field::Type => {
+                    position("type").or_else(|| position("transaction_type"))
+                }
+                None => position(&field.to_string()),
+            };
+
+            if index.is_none() && field.required() {
+                return Err(MappingError::Invalid(format!("No column for {}", field)));
+            }
+        }
+
+        Ok(indexes)
+    }
+}
+
+/// Candidate delimiters, in order of preference when equally likely
+const DELIMITERS: [u8; 4] = [b',', b';', b'\t', b'|'];
+
+/// Number of lines of a sample considered when detecting its delimiter
+const SAMPLE_LINES: usize = 16;
+
+/// Occurrences of `delimiter` outside of quoted fields on `line`
+fn count(line: &[u8], delimiter: u8) -> usize {
+    let mut quoted = false;
+    let mut n = 0;
+    for &b in line {
+        if b == b'"' {
+            quoted = !quoted;
+        } else if b == delimiter && !quoted {
+            n += 1;
+        }
+    }
+    n
+}
+
+/// Detects the delimiter of csv data from a sample of its leading bytes
+///
+/// Delimiters occurring the same number of times on every line are
+/// preferred, followed by the most frequent one. Defaults to `,` when none
+/// occur at all.
+pub fn detect_delimiter(sample: &[u8]) -> u8 {
+    let mut lines = sample
+        .split(|&b| b == b'\n')
+        .filter(|line| !line.trim_ascii().is_empty())
+        .take(SAMPLE_LINES)
+        .collect::<Vec<_>>();
+
+    // The last line of a sample may be cut short
+    if lines.len() > 1 && !sample.ends_with(b"\n") {
+        lines.pop();
+    }
+
+    let mut best = (false, 0, b',');
+    for delimiter in DELIMITERS {
+        let counts = lines
+            .iter()
+            .map(|line| count(line, delimiter))
+            .collect::<Vec<_>>();
+        let total = counts.iter().sum::<usize>();
+        let consistent = total > 0 && counts.windows(2).all(|w| w[0] == w[1]);
+
+        if (consistent, total) > (best.0, best.1) {
+            best = (consistent, total, delimiter);
+        }
+    }
+
+    best.2
+}
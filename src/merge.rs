@@ -0,0 +1,127 @@
+use crate::Transaction;
+use std::{fmt, iter::Peekable, rc::Rc, str::FromStr};
+
+/// Order in which the transactions of several sources are interleaved
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum MergeOrder {
+    /// Every transaction of a source before any of the next
+    #[default]
+    Sequential,
+    /// One transaction from each source in turn
+    RoundRobin,
+    /// Earliest timestamp first, assuming each source is itself ordered by
+    /// timestamp
+    ///
+    /// Transactions without a timestamp are taken as soon as they are
+    /// reached, and ties go to the earlier source.
+    Timestamp,
+}
+
+impl FromStr for MergeOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sequential" => Ok(MergeOrder::Sequential),
+            "round-robin" => Ok(MergeOrder::RoundRobin),
+            "timestamp" => Ok(MergeOrder::Timestamp),
+            _ => Err(format!("Unknown merge order: {}", s)),
+        }
+    }
+}
+
+/// Source and line a transaction was read from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Provenance {
+    source: Rc<str>,
+    line: u64,
+}
+
+impl Provenance {
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn line(&self) -> u64 {
+        self.line
+    }
+}
+
+impl fmt::Display for Provenance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.source, self.line)
+    }
+}
+
+type Rows = Peekable<Box<dyn Iterator<Item = (u64, Transaction)>>>;
+
+/// Single stream of the transactions of several sources, each tagged with
+/// its [Provenance]
+pub struct Merge {
+    order: MergeOrder,
+    sources: Vec<(Rc<str>, Rows)>,
+    /// Source to take from next when merging round-robin
+    turn: usize,
+}
+
+impl Merge {
+    pub fn new(order: MergeOrder) -> Self {
+        Self {
+            order,
+            sources: Vec::new(),
+            turn: 0,
+        }
+    }
+
+    /// Adds a source of transactions along with the line each was read from,
+    /// such as produced by [transcode_lines](crate::transcode_lines)
+    pub fn with_source<I>(mut self, name: &str, rows: I) -> Self
+    where
+        I: IntoIterator<Item = (u64, Transaction)>,
+        I::IntoIter: 'static,
+    {
+        let rows: Box<dyn Iterator<Item = (u64, Transaction)>> = Box::new(rows.into_iter());
+        self.sources.push((name.into(), rows.peekable()));
+        self
+    }
+
+    /// Index of the source to take the next transaction from
+    fn next_source(&mut self) -> Option<usize> {
+        match self.order {
+            MergeOrder::Sequential => {
+                (0..self.sources.len()).find(|&i| self.sources[i].1.peek().is_some())
+            }
+            MergeOrder::RoundRobin => {
+                let n = self.sources.len();
+                let i = (0..n)
+                    .map(|offset| (self.turn + offset) % n)
+                    .find(|&i| self.sources[i].1.peek().is_some())?;
+                self.turn = (i + 1) % n;
+                Some(i)
+            }
+            MergeOrder::Timestamp => self
+                .sources
+                .iter_mut()
+                .enumerate()
+                .filter_map(|(i, (_, rows))| Some((rows.peek()?.1.timestamp(), i)))
+                .min()
+                .map(|(_, i)| i),
+        }
+    }
+}
+
+impl Iterator for Merge {
+    type Item = (Provenance, Transaction);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let i = self.next_source()?;
+        let (source, rows) = &mut self.sources[i];
+        let (line, t) = rows.next()?;
+
+        let provenance = Provenance {
+            source: source.clone(),
+            line,
+        };
+        Some((provenance, t))
+    }
+}
@@ -0,0 +1,178 @@
+use crate::{Client, ClientId};
+use sha2::{Digest, Sha256};
+use std::{fmt, str::FromStr};
+
+/// Domain separation prefixes, such that a leaf can never be passed off as an
+/// internal node
+const LEAF: u8 = 0x00;
+const NODE: u8 = 0x01;
+
+pub type Hash = [u8; 32];
+
+/// Canonical form of a client row, identical to its csv output
+pub fn client_row(client: &Client) -> String {
+    format!(
+        "{},{},{},{},{}",
+        client.id(),
+        client.available(),
+        client.held(),
+        client.total(),
+        client.locked()
+    )
+}
+
+fn leaf_hash(row: &str) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([LEAF]);
+    hasher.update(row.as_bytes());
+    hasher.finalize().into()
+}
+
+fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([NODE]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// Side on which a sibling hash is combined
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// Path of sibling hashes from a client's leaf up to the root
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MerkleProof {
+    siblings: Vec<(Side, Hash)>,
+}
+
+impl MerkleProof {
+    /// Root hash implied by the proof for the given client row
+    pub fn root(&self, row: &str) -> Hash {
+        self.siblings
+            .iter()
+            .fold(leaf_hash(row), |hash, (side, sibling)| match side {
+                Side::Left => node_hash(sibling, &hash),
+                Side::Right => node_hash(&hash, sibling),
+            })
+    }
+
+    /// Verifies that the client `row` is committed to by `root`
+    pub fn verify(&self, root: &Hash, row: &str) -> bool {
+        &self.root(row) == root
+    }
+}
+
+/// Formats the proof as `;` separated siblings, each prefixed by `L:` or `R:`
+impl fmt::Display for MerkleProof {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (side, hash)) in self.siblings.iter().enumerate() {
+            if i > 0 {
+                write!(f, ";")?;
+            }
+            let side = match side {
+                Side::Left => "L",
+                Side::Right => "R",
+            };
+            write!(f, "{}:{}", side, hex::encode(hash))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for MerkleProof {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let siblings = s
+            .split(';')
+            .filter(|sibling| !sibling.is_empty())
+            .map(|sibling| {
+                let (side, hash) = sibling
+                    .split_once(':')
+                    .ok_or_else(|| format!("Malformed sibling: {}", sibling))?;
+                let side = match side {
+                    "L" => Side::Left,
+                    "R" => Side::Right,
+                    _ => return Err(format!("Unknown side: {}", side)),
+                };
+                let mut bytes = [0; 32];
+                hex::decode_to_slice(hash, &mut bytes).map_err(|e| e.to_string())?;
+                Ok((side, bytes))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { siblings })
+    }
+}
+
+/// Merkle tree committing to every client row in client id order
+///
+/// A level with an odd number of nodes promotes its last node unchanged to
+/// the level above.
+pub struct MerkleTree {
+    clients: Vec<ClientId>,
+    levels: Vec<Vec<Hash>>,
+}
+
+impl MerkleTree {
+    pub fn new<'a, I>(clients: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Client>,
+    {
+        let (clients, leaves): (Vec<ClientId>, Vec<Hash>) = clients
+            .into_iter()
+            .map(|c| (c.id(), leaf_hash(&client_row(c))))
+            .unzip();
+
+        let mut levels = vec![leaves];
+        while levels.last().unwrap().len() > 1 {
+            let level = levels
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => node_hash(left, right),
+                    [single] => *single,
+                    _ => unreachable!(),
+                })
+                .collect();
+            levels.push(level);
+        }
+
+        Self { clients, levels }
+    }
+
+    /// Root hash, or all zeroes for a tree without clients
+    pub fn root(&self) -> Hash {
+        self.levels
+            .last()
+            .and_then(|level| level.first())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Inclusion proof of the client's row
+    pub fn proof(&self, client: ClientId) -> Option<MerkleProof> {
+        let mut index = self.clients.binary_search(&client).ok()?;
+
+        let mut siblings = vec![];
+        for level in &self.levels[..self.levels.len() - 1] {
+            let sibling = index ^ 1;
+            if sibling < level.len() {
+                let side = if sibling < index {
+                    Side::Left
+                } else {
+                    Side::Right
+                };
+                siblings.push((side, level[sibling]));
+            }
+            index /= 2;
+        }
+
+        Some(MerkleProof { siblings })
+    }
+}
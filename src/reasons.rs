@@ -0,0 +1,92 @@
+use crate::{Authority, DisputeTransactionType, TxId};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// Row of the per-reason dispute report
+///
+/// Disputes raised without a reason code are summarized under no reason.
+/// Chargebacks count towards the reason of the dispute they charge back.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ReasonSummary {
+    reason: Option<String>,
+    disputes: u64,
+    disputed: Decimal,
+    chargebacks: u64,
+    charged_back: Decimal,
+}
+
+impl ReasonSummary {
+    fn new(reason: Option<String>) -> Self {
+        Self {
+            reason,
+            disputes: 0,
+            disputed: Decimal::new(0, 4),
+            chargebacks: 0,
+            charged_back: Decimal::new(0, 4),
+        }
+    }
+
+    pub fn reason(&self) -> Option<&str> {
+        self.reason.as_deref()
+    }
+
+    /// Number of disputes opened
+    pub fn disputes(&self) -> u64 {
+        self.disputes
+    }
+
+    /// Sum of amounts held by disputes
+    pub fn disputed(&self) -> Decimal {
+        self.disputed
+    }
+
+    /// Number of disputes charged back
+    pub fn chargebacks(&self) -> u64 {
+        self.chargebacks
+    }
+
+    /// Sum of amounts charged back
+    pub fn charged_back(&self) -> Decimal {
+        self.charged_back
+    }
+}
+
+impl Authority {
+    /// Summarizes the dispute history by reason code, in order of reason
+    pub fn dispute_reasons(&self) -> Vec<ReasonSummary> {
+        let mut summaries = BTreeMap::<Option<&str>, ReasonSummary>::new();
+        // Reason of each transaction's open dispute
+        let mut open = HashMap::<TxId, Option<&str>>::new();
+
+        for t in &self.dispute_history {
+            let amount = t.amount().unwrap_or_default();
+            match t.transaction_type() {
+                DisputeTransactionType::Dispute => {
+                    open.insert(t.tx(), t.reason());
+
+                    let summary = summaries
+                        .entry(t.reason())
+                        .or_insert_with(|| ReasonSummary::new(t.reason().map(String::from)));
+                    summary.disputes += 1;
+                    summary.disputed += amount;
+                }
+                DisputeTransactionType::Chargeback => {
+                    let reason = open.remove(&t.tx()).flatten();
+
+                    let summary = summaries
+                        .entry(reason)
+                        .or_insert_with(|| ReasonSummary::new(reason.map(String::from)));
+                    summary.chargebacks += 1;
+                    summary.charged_back += amount;
+                }
+                DisputeTransactionType::Resolve => {
+                    open.remove(&t.tx());
+                }
+                DisputeTransactionType::Reversal => {}
+            }
+        }
+
+        summaries.into_values().collect()
+    }
+}
@@ -0,0 +1,115 @@
+use crate::{ClientId, Transaction, TxId};
+use csv::Reader;
+use ed25519_dalek::{Signature, Signer, Verifier};
+pub use ed25519_dalek::{SigningKey, VerifyingKey};
+use serde::Deserialize;
+use std::{collections::HashMap, io::Read};
+
+#[derive(thiserror::Error, Debug)]
+pub enum SignatureError {
+    #[error("Transaction with tx: {0} is missing a signature")]
+    Missing(TxId),
+    #[error("Transaction with tx: {0} has an invalid signature for client: {1}")]
+    Invalid(TxId, ClientId),
+    #[error("Transaction with tx: {0} issued by client: {1} without a registered key")]
+    UnknownClient(TxId, ClientId),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum KeyError {
+    #[error(transparent)]
+    Csv(#[from] csv::Error),
+    #[error("Invalid key for client: {0}")]
+    Invalid(ClientId),
+}
+
+/// Bytes signed by the issuing client, the transaction's canonical row
+pub fn message(t: &Transaction) -> Vec<u8> {
+    t.to_string().into_bytes()
+}
+
+/// Signs `t` on behalf of its issuing client
+pub fn sign(t: Transaction, key: &SigningKey) -> Transaction {
+    let signature = key.sign(&message(&t));
+    t.with_signature(signature.to_bytes())
+}
+
+/// Raw key row as found in key files, with the key hex encoded
+///
+/// ```csv
+/// client,key
+/// 1,9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60
+/// ```
+#[derive(Deserialize)]
+struct RawKey {
+    client: ClientId,
+    key: String,
+}
+
+/// Reads hex encoded 32 byte keys by client
+fn read_keys<T>(rdr: Reader<T>) -> Result<Vec<(ClientId, [u8; 32])>, KeyError>
+where
+    T: Read,
+{
+    rdr.into_deserialize::<RawKey>()
+        .map(|r| {
+            let r = r?;
+            let mut key = [0; 32];
+            hex::decode_to_slice(r.key, &mut key).map_err(|_| KeyError::Invalid(r.client))?;
+            Ok((r.client, key))
+        })
+        .collect()
+}
+
+/// Reads client signing keys, as used to sign test files
+pub fn read_signing_keys<T>(rdr: Reader<T>) -> Result<HashMap<ClientId, SigningKey>, KeyError>
+where
+    T: Read,
+{
+    Ok(read_keys(rdr)?
+        .into_iter()
+        .map(|(client, key)| (client, SigningKey::from_bytes(&key)))
+        .collect())
+}
+
+/// Registry of client Ed25519 public keys which transactions issued by each
+/// client must be signed with
+#[derive(Default)]
+pub struct KeyRegistry {
+    keys: HashMap<ClientId, VerifyingKey>,
+}
+
+impl KeyRegistry {
+    /// Reads a registry from a key file of public keys
+    pub fn from_reader<T>(rdr: Reader<T>) -> Result<Self, KeyError>
+    where
+        T: Read,
+    {
+        let keys = read_keys(rdr)?
+            .into_iter()
+            .map(|(client, key)| {
+                VerifyingKey::from_bytes(&key)
+                    .map(|key| (client, key))
+                    .map_err(|_| KeyError::Invalid(client))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { keys })
+    }
+
+    pub fn insert(&mut self, client: ClientId, key: VerifyingKey) {
+        self.keys.insert(client, key);
+    }
+
+    /// Verifies `t` is signed by its issuing client
+    pub(crate) fn verify(&self, t: &Transaction) -> Result<(), SignatureError> {
+        let key = self
+            .keys
+            .get(&t.client())
+            .ok_or(SignatureError::UnknownClient(t.tx(), t.client()))?;
+        let signature = t.signature().ok_or(SignatureError::Missing(t.tx()))?;
+
+        key.verify(&message(t), &Signature::from_bytes(signature))
+            .map_err(|_| SignatureError::Invalid(t.tx(), t.client()))
+    }
+}
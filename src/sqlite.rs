@@ -0,0 +1,225 @@
+use crate::{Authority, Client, TxId};
+use rusqlite::{
+    params,
+    types::{ToSql, ToSqlOutput},
+    Connection,
+};
+use std::{io, path::Path};
+
+#[derive(thiserror::Error, Debug)]
+pub enum ExportError {
+    #[error(transparent)]
+    Sqlite(#[from] rusqlite::Error),
+    #[error(transparent)]
+    Storage(#[from] io::Error),
+}
+
+/// Schema of the exported database
+///
+/// Amounts are stored with `NUMERIC` affinity such that they may be compared
+/// and aggregated directly in SQL. Client and transaction ids, as well as
+/// timestamps, are stored as `TEXT`, as unsigned 64-bit values may not fit a
+/// SQLite integer.
+const SCHEMA: &str = "
+DROP TABLE IF EXISTS clients;
+DROP TABLE IF EXISTS wallets;
+DROP TABLE IF EXISTS transactions;
+DROP TABLE IF EXISTS disputes;
+DROP TABLE IF EXISTS dispute_history;
+DROP TABLE IF EXISTS escrows;
+
+CREATE TABLE clients (
+    client TEXT PRIMARY KEY,
+    available NUMERIC NOT NULL,
+    held NUMERIC NOT NULL,
+    total NUMERIC NOT NULL,
+    debt NUMERIC NOT NULL,
+    locked BOOLEAN NOT NULL,
+    closed BOOLEAN NOT NULL,
+    kyc TEXT
+);
+
+CREATE TABLE wallets (
+    client TEXT NOT NULL REFERENCES clients (client),
+    wallet INTEGER NOT NULL,
+    available NUMERIC NOT NULL,
+    held NUMERIC NOT NULL,
+    total NUMERIC NOT NULL,
+    PRIMARY KEY (client, wallet)
+);
+
+CREATE TABLE transactions (
+    tx TEXT PRIMARY KEY,
+    type TEXT NOT NULL,
+    client TEXT NOT NULL,
+    amount NUMERIC NOT NULL,
+    timestamp TEXT,
+    reversed BOOLEAN NOT NULL,
+    charged_back NUMERIC NOT NULL,
+    wallet INTEGER NOT NULL
+);
+
+CREATE TABLE disputes (
+    tx TEXT PRIMARY KEY REFERENCES transactions (tx),
+    client TEXT NOT NULL,
+    amount NUMERIC NOT NULL,
+    timestamp TEXT,
+    reason TEXT,
+    reference TEXT
+);
+
+CREATE TABLE dispute_history (
+    seq INTEGER PRIMARY KEY,
+    type TEXT NOT NULL,
+    client TEXT NOT NULL,
+    tx TEXT NOT NULL,
+    amount NUMERIC NOT NULL,
+    timestamp TEXT,
+    reason TEXT,
+    reference TEXT
+);
+
+CREATE TABLE escrows (
+    tx TEXT PRIMARY KEY,
+    payer TEXT NOT NULL,
+    payee TEXT NOT NULL,
+    wallet INTEGER NOT NULL,
+    amount NUMERIC NOT NULL,
+    status TEXT NOT NULL,
+    opened_at TEXT,
+    settled_at TEXT
+);
+
+CREATE INDEX transactions_client ON transactions (client);
+CREATE INDEX dispute_history_tx ON dispute_history (tx);
+";
+
+impl ToSql for TxId {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.to_string()))
+    }
+}
+
+impl Authority {
+    /// Persists client state, the transaction ledger, open disputes and
+    /// dispute history into the SQLite database at `path`
+    ///
+    /// Tables of a previous export are replaced. Transactions evicted from the
+    /// transaction ledger are not exported.
+    pub fn export_sqlite<P>(&self, path: P) -> Result<(), ExportError>
+    where
+        P: AsRef<Path>,
+    {
+        let mut conn = Connection::open(path)?;
+        let db = conn.transaction()?;
+        db.execute_batch(SCHEMA)?;
+
+        {
+            let mut insert = db.prepare(
+                "INSERT INTO clients (client, available, held, total, debt, locked, closed, kyc)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )?;
+            for client in self.client_state.values() {
+                insert.execute(params![
+                    client.id().to_string(),
+                    client.available().to_string(),
+                    client.held().to_string(),
+                    client.total().to_string(),
+                    client.debt().to_string(),
+                    client.locked(),
+                    client.closed(),
+                    client.kyc().map(|kyc| kyc.to_string()),
+                ])?;
+            }
+
+            let mut insert = db.prepare(
+                "INSERT INTO wallets (client, wallet, available, held, total)
+                VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for wallet in self.client_state.values().flat_map(Client::wallets) {
+                insert.execute(params![
+                    wallet.client().to_string(),
+                    wallet.wallet(),
+                    wallet.available().to_string(),
+                    wallet.held().to_string(),
+                    wallet.total().to_string(),
+                ])?;
+            }
+
+            let mut insert = db.prepare(
+                "INSERT INTO transactions (tx, type, client, amount, timestamp, reversed, charged_back, wallet)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )?;
+            for tx in self.transaction_ledger.keys() {
+                if let Some(t) = self.transaction_ledger.get(tx)? {
+                    insert.execute(params![
+                        t.tx(),
+                        t.transaction_type().to_string(),
+                        t.client().to_string(),
+                        t.amount().to_string(),
+                        t.timestamp().map(|ts| ts.to_string()),
+                        t.reversed(),
+                        t.charged_back().to_string(),
+                        t.wallet(),
+                    ])?;
+                }
+            }
+
+            let mut insert = db.prepare(
+                "INSERT INTO disputes (tx, client, amount, timestamp, reason, reference)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            for tx in self.dispute_ledger.keys() {
+                if let (Some(t), Some(disputed)) = (
+                    self.dispute_ledger.get(tx)?,
+                    self.transaction_ledger.get(tx)?,
+                ) {
+                    insert.execute(params![
+                        t.tx(),
+                        t.client().to_string(),
+                        t.amount().unwrap_or(disputed.amount()).to_string(),
+                        t.timestamp().map(|ts| ts.to_string()),
+                        t.reason(),
+                        t.reference(),
+                    ])?;
+                }
+            }
+
+            let mut insert = db.prepare(
+                "INSERT INTO dispute_history (type, client, tx, amount, timestamp, reason, reference)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )?;
+            for t in &self.dispute_history {
+                insert.execute(params![
+                    t.transaction_type().to_string(),
+                    t.client().to_string(),
+                    t.tx(),
+                    t.amount().map(|amount| amount.to_string()),
+                    t.timestamp().map(|ts| ts.to_string()),
+                    t.reason(),
+                    t.reference(),
+                ])?;
+            }
+
+            let mut insert = db.prepare(
+                "INSERT INTO escrows (tx, payer, payee, wallet, amount, status, opened_at, settled_at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )?;
+            for escrow in self.escrows.values() {
+                insert.execute(params![
+                    escrow.tx(),
+                    escrow.payer().to_string(),
+                    escrow.payee().to_string(),
+                    escrow.wallet(),
+                    escrow.amount().to_string(),
+                    escrow.status().to_string(),
+                    escrow.opened_at().map(|ts| ts.to_string()),
+                    escrow.settled_at().map(|ts| ts.to_string()),
+                ])?;
+            }
+        }
+
+        db.commit()?;
+        Ok(())
+    }
+}
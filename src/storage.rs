@@ -0,0 +1,376 @@
+use crate::{
+    DisputeTransaction, DisputeTransactionType, OperationTransaction, OperationTransactionType,
+    TxId,
+};
+use rust_decimal::Decimal;
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    hash::Hash,
+    io::{self, Read, Seek, SeekFrom, Write},
+    marker::PhantomData,
+    path::Path,
+};
+
+/// Key value store backing the ledgers of an [Authority](crate::Authority)
+///
+/// Values are returned owned, as backends need not keep them in memory.
+pub trait Storage<K, V> {
+    fn get(&self, key: &K) -> io::Result<Option<V>>;
+
+    fn contains(&self, key: &K) -> bool;
+
+    /// Inserts `value`, replacing any value previously stored under `key`
+    fn insert(&mut self, key: K, value: V) -> io::Result<()>;
+
+    fn remove(&mut self, key: &K) -> io::Result<()>;
+
+    fn len(&self) -> usize;
+
+    /// Iterator across stored keys, in no particular order
+    fn keys(&self) -> Box<dyn Iterator<Item = &K> + '_>;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// In memory [Storage] backed by a [HashMap]
+pub struct MemoryStorage<K, V> {
+    map: HashMap<K, V>,
+}
+
+impl<K, V> Default for MemoryStorage<K, V> {
+    fn default() -> Self {
+        Self {
+            map: HashMap::new(),
+        }
+    }
+}
+
+impl<K, V> Storage<K, V> for MemoryStorage<K, V>
+where
+    K: Eq + Hash,
+    V: Clone,
+{
+    fn get(&self, key: &K) -> io::Result<Option<V>> {
+        Ok(self.map.get(key).cloned())
+    }
+
+    fn contains(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    fn insert(&mut self, key: K, value: V) -> io::Result<()> {
+        self.map.insert(key, value);
+        Ok(())
+    }
+
+    fn remove(&mut self, key: &K) -> io::Result<()> {
+        self.map.remove(key);
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    fn keys(&self) -> Box<dyn Iterator<Item = &K> + '_> {
+        Box::new(self.map.keys())
+    }
+}
+
+/// Value which can be written to and read back from a [FileStorage]
+pub trait Record: Sized {
+    fn encode(&self, buf: &mut Vec<u8>);
+
+    fn decode(buf: &[u8]) -> io::Result<Self>;
+}
+
+/// On disk [Storage] backed by an append-only file
+///
+/// Only an index of each key to the offset of its latest record is kept in
+/// memory. Replaced and removed records are never reclaimed, as the file is
+/// expected to live only as long as a single run.
+pub struct FileStorage<K, V> {
+    file: File,
+    index: HashMap<K, (u64, u32)>,
+    end: u64,
+    record: PhantomData<V>,
+}
+
+impl<K, V> FileStorage<K, V> {
+    /// Creates a store at `path`, truncating any existing file
+    pub fn create<P>(path: P) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+
+        Ok(Self {
+            file,
+            index: HashMap::new(),
+            end: 0,
+            record: PhantomData,
+        })
+    }
+}
+
+impl<K, V> Storage<K, V> for FileStorage<K, V>
+where
+    K: Eq + Hash,
+    V: Record,
+{
+    fn get(&self, key: &K) -> io::Result<Option<V>> {
+        let (offset, len) = match self.index.get(key) {
+            Some(&location) => location,
+            None => return Ok(None),
+        };
+
+        let mut buf = vec![0; len as usize];
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut buf)?;
+
+        V::decode(&buf).map(Some)
+    }
+
+    fn contains(&self, key: &K) -> bool {
+        self.index.contains_key(key)
+    }
+
+    fn insert(&mut self, key: K, value: V) -> io::Result<()> {
+        let mut buf = vec![];
+        value.encode(&mut buf);
+
+        self.file.seek(SeekFrom::Start(self.end))?;
+        self.file.write_all(&buf)?;
+
+        self.index.insert(key, (self.end, buf.len() as u32));
+        self.end += buf.len() as u64;
+        Ok(())
+    }
+
+    fn remove(&mut self, key: &K) -> io::Result<()> {
+        self.index.remove(key);
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    fn keys(&self) -> Box<dyn Iterator<Item = &K> + '_> {
+        Box::new(self.index.keys())
+    }
+}
+
+/// Cursor over an encoded [Record]
+struct Decoder<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    fn take<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        if self.buf.len() < N {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Truncated record",
+            ));
+        }
+
+        let (head, tail) = self.buf.split_at(N);
+        self.buf = tail;
+        Ok(head.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        Ok(u16::from_le_bytes(self.take()?))
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+
+    fn tx_id(&mut self) -> io::Result<TxId> {
+        match self.u8()? {
+            0 => self.u64().map(TxId::Number),
+            1 => Ok(TxId::Uuid(u128::from_le_bytes(self.take()?))),
+            _ => Err(invalid("Unknown transaction id kind")),
+        }
+    }
+
+    fn decimal(&mut self) -> io::Result<Decimal> {
+        Ok(Decimal::deserialize(self.take()?))
+    }
+
+    fn option_u64(&mut self) -> io::Result<Option<u64>> {
+        match self.u8()? {
+            0 => Ok(None),
+            _ => self.u64().map(Some),
+        }
+    }
+
+    fn option_string(&mut self) -> io::Result<Option<String>> {
+        if self.u8()? == 0 {
+            return Ok(None);
+        }
+
+        let len = u32::from_le_bytes(self.take()?) as usize;
+        if self.buf.len() < len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Truncated record",
+            ));
+        }
+
+        let (head, tail) = self.buf.split_at(len);
+        self.buf = tail;
+        String::from_utf8(head.to_vec())
+            .map(Some)
+            .map_err(|_| invalid("Invalid UTF-8 string"))
+    }
+}
+
+fn encode_option_u64(value: Option<u64>, buf: &mut Vec<u8>) {
+    match value {
+        Some(value) => {
+            buf.push(1);
+            buf.extend_from_slice(&value.to_le_bytes());
+        }
+        None => buf.push(0),
+    }
+}
+
+fn encode_option_str(value: Option<&str>, buf: &mut Vec<u8>) {
+    match value {
+        Some(value) => {
+            buf.push(1);
+            buf.extend_from_slice(&(value.len() as u32).to_le_bytes());
+            buf.extend_from_slice(value.as_bytes());
+        }
+        None => buf.push(0),
+    }
+}
+
+fn encode_tx_id(tx: TxId, buf: &mut Vec<u8>) {
+    match tx {
+        TxId::Number(tx) => {
+            buf.push(0);
+            buf.extend_from_slice(&tx.to_le_bytes());
+        }
+        TxId::Uuid(uuid) => {
+            buf.push(1);
+            buf.extend_from_slice(&uuid.to_le_bytes());
+        }
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+impl Record for OperationTransaction {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.push(match self.transaction_type() {
+            OperationTransactionType::Deposit => 0,
+            OperationTransactionType::Withdrawal => 1,
+        });
+        buf.extend_from_slice(&self.client().to_le_bytes());
+        encode_tx_id(self.tx(), buf);
+        buf.extend_from_slice(&self.amount().serialize());
+        encode_option_u64(self.timestamp(), buf);
+        buf.push(self.reversed() as u8);
+        buf.extend_from_slice(&self.charged_back().serialize());
+        buf.extend_from_slice(&self.wallet().to_le_bytes());
+    }
+
+    fn decode(buf: &[u8]) -> io::Result<Self> {
+        let mut d = Decoder { buf };
+
+        let transaction_type = match d.u8()? {
+            0 => OperationTransactionType::Deposit,
+            1 => OperationTransactionType::Withdrawal,
+            _ => return Err(invalid("Unknown operation transaction type")),
+        };
+        let t = OperationTransaction::new(transaction_type, d.u64()?, d.tx_id()?, d.decimal()?);
+        let mut t = match d.option_u64()? {
+            Some(timestamp) => t.with_timestamp(timestamp),
+            None => t,
+        };
+
+        if d.u8()? != 0 {
+            t.set_reversed();
+        }
+        t.add_charged_back(d.decimal()?);
+        Ok(t.with_wallet(d.u16()?))
+    }
+}
+
+impl Record for DisputeTransaction {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.push(match self.transaction_type() {
+            DisputeTransactionType::Dispute => 0,
+            DisputeTransactionType::Resolve => 1,
+            DisputeTransactionType::Chargeback => 2,
+            DisputeTransactionType::Reversal => 3,
+        });
+        buf.extend_from_slice(&self.client().to_le_bytes());
+        encode_tx_id(self.tx(), buf);
+        match self.amount() {
+            Some(amount) => {
+                buf.push(1);
+                buf.extend_from_slice(&amount.serialize());
+            }
+            None => buf.push(0),
+        }
+        encode_option_u64(self.timestamp(), buf);
+        encode_option_str(self.reason(), buf);
+        encode_option_str(self.reference(), buf);
+        buf.extend_from_slice(&self.opened_seq().to_le_bytes());
+        encode_option_u64(self.opened_at(), buf);
+    }
+
+    fn decode(buf: &[u8]) -> io::Result<Self> {
+        let mut d = Decoder { buf };
+
+        let transaction_type = match d.u8()? {
+            0 => DisputeTransactionType::Dispute,
+            1 => DisputeTransactionType::Resolve,
+            2 => DisputeTransactionType::Chargeback,
+            3 => DisputeTransactionType::Reversal,
+            _ => return Err(invalid("Unknown dispute transaction type")),
+        };
+        let t = DisputeTransaction::new(transaction_type, d.u64()?, d.tx_id()?);
+        let t = match d.u8()? {
+            0 => t,
+            _ => t.with_amount(d.decimal()?),
+        };
+
+        let t = match d.option_u64()? {
+            Some(timestamp) => t.with_timestamp(timestamp),
+            None => t,
+        };
+        let t = match d.option_string()? {
+            Some(reason) => t.with_reason(reason),
+            None => t,
+        };
+
+        let mut t = match d.option_string()? {
+            Some(reference) => t.with_reference(reference),
+            None => t,
+        };
+
+        t.set_opened(d.u64()?, d.option_u64()?);
+        Ok(t)
+    }
+}
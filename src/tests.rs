@@ -1,25 +1,61 @@
 use crate::{
-    Authority, Client, DisputeTransaction,
+    sign, Account, AccountError, AccountTransaction, AccountTransactionType, Authority, BatchError,
+    Client, ClientId, CollectionPolicy, Deadline, DeadlinePolicy, DisputeError, DisputeTransaction,
     DisputeTransactionType::{self, *},
-    OperationTransaction,
+    EscrowError, EscrowStatus, EscrowTransaction, EscrowTransactionType, FileStorage, KeyRegistry,
+    KycStatus, Limit, Limits, OperationError, OperationTransaction,
     OperationTransactionType::{self, *},
-    Transaction,
+    Outcome, SignatureError, SigningKey, Transaction, TransactionError, TxId, Violation, Window,
 };
 use pretty_assertions::assert_eq;
 use rust_decimal::Decimal;
+use std::time::Duration;
+use tempfile::TempDir;
 
-fn operation(tt: OperationTransactionType, client: u16, tx: u32, amount: Decimal) -> Transaction {
-    Transaction::Operation(OperationTransaction::new(tt, client, tx, amount))
+fn operation(
+    tt: OperationTransactionType,
+    client: ClientId,
+    tx: u64,
+    amount: Decimal,
+) -> Transaction {
+    Transaction::Operation(OperationTransaction::new(tt, client, tx.into(), amount))
 }
 
-fn dispute(tt: DisputeTransactionType, client: u16, tx: u32) -> Transaction {
-    Transaction::Dispute(DisputeTransaction::new(tt, client, tx))
+fn dispute(tt: DisputeTransactionType, client: ClientId, tx: u64) -> Transaction {
+    Transaction::Dispute(DisputeTransaction::new(tt, client, tx.into()))
+}
+
+fn account(tt: AccountTransactionType, client: ClientId, tx: u64) -> Transaction {
+    Transaction::Account(AccountTransaction::new(tt, client, tx.into()))
+}
+
+fn escrow(tt: EscrowTransactionType, client: ClientId, tx: u64) -> Transaction {
+    Transaction::Escrow(EscrowTransaction::new(tt, client, tx.into()))
 }
 
 fn d(number: i64) -> Decimal {
     Decimal::from(number)
 }
 
+/// Transactions up to `max_tx` retained in the transaction ledger
+fn ledgered(a: &Authority, max_tx: u64) -> Vec<u64> {
+    (1..=max_tx)
+        .filter(|&tx| a.transaction_ledger.contains(&tx.into()))
+        .collect()
+}
+
+/// Authority storing its ledgers in files under a temp directory, removed
+/// when the returned guard is dropped
+fn file_backed() -> (TempDir, Authority) {
+    let dir = TempDir::new().unwrap();
+    let authority = Authority::default().with_storage(
+        FileStorage::create(dir.path().join("transactions")).unwrap(),
+        FileStorage::create(dir.path().join("disputes")).unwrap(),
+    );
+
+    (dir, authority)
+}
+
 #[test]
 fn deposit() {
     let mut a = Authority::default();
@@ -215,3 +251,1009 @@ fn dispute_chargeback_error() {
         a.iter_clients().collect::<Vec<&Client>>()
     );
 }
+
+#[test]
+fn withdraw_limit_count() {
+    let limits = Limits::new(vec![Limit::new(
+        None,
+        Window::Transactions(3),
+        Some(1),
+        None,
+    )]);
+
+    let mut a = Authority::default().with_limits(limits);
+    a.apply_iter(
+        vec![
+            operation(Deposit, 1, 1, d(10)),
+            operation(Withdrawal, 1, 2, d(1)),
+            // Second withdrawal within 3 transactions
+            operation(Withdrawal, 1, 3, d(1)),
+            operation(Deposit, 1, 4, d(1)),
+            // Withdrawal of tx 2 still within window
+            operation(Withdrawal, 1, 5, d(1)),
+            operation(Deposit, 1, 6, d(1)),
+            operation(Withdrawal, 1, 7, d(1)),
+            // Other clients are tracked separately
+            operation(Deposit, 2, 8, d(1)),
+            operation(Withdrawal, 2, 9, d(1)),
+        ]
+        .into_iter(),
+    );
+
+    assert_eq!(
+        vec![
+            &Client::test(1, 10, 0, 10, false),
+            &Client::test(2, 0, 0, 0, false),
+        ],
+        a.iter_clients().collect::<Vec<&Client>>()
+    );
+}
+
+#[test]
+fn withdraw_limit_amount() {
+    let limits = Limits::new(vec![
+        Limit::new(None, Window::Day, None, Some(d(3))),
+        // Client specific limits replace the defaults
+        Limit::new(Some(2), Window::Day, None, Some(d(5))),
+    ]);
+
+    let mut a = Authority::default().with_limits(limits);
+    a.apply_iter(
+        vec![
+            operation(Deposit, 1, 1, d(10)),
+            operation(Deposit, 2, 2, d(10)),
+            operation(Withdrawal, 1, 3, d(2)),
+            // Would exceed 3 per day
+            operation(Withdrawal, 1, 4, d(2)),
+            operation(Withdrawal, 1, 5, d(1)),
+            operation(Withdrawal, 2, 6, d(5)),
+            operation(Withdrawal, 2, 7, d(1)),
+            // Next day, according to its timestamp
+            operation(Withdrawal, 1, 8, d(1)).with_timestamp(86_400),
+            // Falls on the day of the latest timestamp seen
+            operation(Withdrawal, 1, 9, d(3)),
+            operation(Withdrawal, 1, 10, d(2)).with_timestamp(172_800),
+            // Back in time, the previous day is still tracked
+            operation(Withdrawal, 1, 11, d(1)).with_timestamp(86_400),
+            // Usage of the later day survives going back in time
+            operation(Withdrawal, 1, 12, d(2)).with_timestamp(172_800),
+        ]
+        .into_iter(),
+    );
+
+    assert_eq!(
+        vec![
+            &Client::test(1, 3, 0, 3, false),
+            &Client::test(2, 5, 0, 5, false),
+        ],
+        a.iter_clients().collect::<Vec<&Client>>()
+    );
+}
+
+#[test]
+fn dispute_window() {
+    let mut a = Authority::default().with_dispute_window(Duration::from_secs(100));
+    a.apply_iter(
+        vec![
+            operation(Deposit, 1, 1, d(1)).with_timestamp(0),
+            operation(Deposit, 1, 2, d(2)).with_timestamp(50),
+            // Transactions without a timestamp may always be disputed
+            operation(Deposit, 1, 3, d(4)),
+            operation(Deposit, 2, 4, d(1)).with_timestamp(120),
+            // Window of tx 1 expired according to latest timestamp seen
+            dispute(Dispute, 1, 1),
+            dispute(Dispute, 1, 2).with_timestamp(150),
+            dispute(Dispute, 1, 3).with_timestamp(1000),
+        ]
+        .into_iter(),
+    );
+
+    assert_eq!(
+        vec![
+            &Client::test(1, 1, 6, 7, false),
+            &Client::test(2, 1, 0, 1, false),
+        ],
+        a.iter_clients().collect::<Vec<&Client>>()
+    );
+}
+
+#[test]
+fn compaction_max_age() {
+    let mut a = Authority::default().with_max_tx_age(2);
+    a.apply_iter(
+        vec![
+            operation(Deposit, 1, 1, d(1)),
+            operation(Deposit, 1, 2, d(2)),
+            dispute(Dispute, 1, 2),
+            operation(Deposit, 1, 3, d(4)),
+            operation(Deposit, 1, 4, d(8)),
+            // Transaction 1 was evicted but its tx is still taken
+            operation(Deposit, 1, 1, d(16)),
+            // Transaction 1 can no longer be disputed
+            dispute(Dispute, 1, 1),
+            // Transaction 2 was retained while under dispute
+            dispute(Resolve, 1, 2),
+        ]
+        .into_iter(),
+    );
+
+    assert_eq!(
+        vec![&Client::test(1, 15, 0, 15, false)],
+        a.iter_clients().collect::<Vec<&Client>>()
+    );
+
+    // Transaction 2 is evicted on the next compaction after its resolution
+    a.apply_iter(vec![operation(Deposit, 1, 5, d(16))].into_iter());
+    assert_eq!(vec![4, 5], ledgered(&a, 5));
+
+    // Exact resubmissions of evicted transactions are no longer recognised
+    // as duplicates, and are rejected like conflicting ones
+    assert!(matches!(
+        a.apply(operation(Deposit, 1, 1, d(1))),
+        Err(TransactionError::Operation(
+            OperationError::TransactionExists(_)
+        ))
+    ));
+}
+
+#[test]
+fn compaction_dispute_window() {
+    let mut a = Authority::default().with_dispute_window(Duration::from_secs(100));
+    a.apply_iter(
+        vec![
+            operation(Deposit, 1, 1, d(1)).with_timestamp(0),
+            operation(Deposit, 1, 2, d(2)),
+            operation(Deposit, 1, 3, d(4)).with_timestamp(101),
+            operation(Deposit, 1, 1, d(8)).with_timestamp(102),
+            dispute(Dispute, 1, 1),
+        ]
+        .into_iter(),
+    );
+
+    assert_eq!(
+        vec![&Client::test(1, 7, 0, 7, false)],
+        a.iter_clients().collect::<Vec<&Client>>()
+    );
+
+    assert_eq!(vec![2, 3], ledgered(&a, 3));
+
+    // Transactions timestamped out of order are evicted once due, regardless
+    // of those ledgered before them
+    a.apply_iter(
+        vec![
+            operation(Deposit, 1, 4, d(16)).with_timestamp(500),
+            operation(Deposit, 1, 5, d(32)).with_timestamp(150),
+            operation(Deposit, 1, 6, d(64)).with_timestamp(450),
+        ]
+        .into_iter(),
+    );
+    assert_eq!(vec![2, 4, 6], ledgered(&a, 6));
+}
+
+#[test]
+fn storage_equivalence() {
+    let transactions = vec![
+        operation(Deposit, 1, 1, d(1)),
+        operation(Deposit, 1, 2, d(2)).with_timestamp(10),
+        operation(Withdrawal, 1, 3, d(2)),
+        operation(Deposit, 2, 4, d(5)),
+        operation(Deposit, 2, 4, d(5)),
+        dispute(Dispute, 2, 2),
+        dispute(Resolve, 2, 2),
+        dispute(Dispute, 1, 3),
+        dispute(Chargeback, 1, 3),
+        dispute(Dispute, 1, 4),
+        operation(Withdrawal, 2, 5, d(6)),
+        dispute(Dispute, 3, 2),
+        operation(Deposit, 3, 6, d(1)).with_timestamp(200),
+        dispute(Resolve, 3, 2),
+        dispute(Resolve, 1, 4),
+        dispute(Chargeback, 2, 4),
+    ];
+
+    let mut memory = Authority::default().with_max_tx_age(4);
+    memory.apply_iter(transactions.clone().into_iter());
+
+    let (_dir, file) = file_backed();
+    let mut file = file.with_max_tx_age(4);
+    file.apply_iter(transactions.into_iter());
+
+    assert_eq!(
+        memory.iter_clients().collect::<Vec<&Client>>(),
+        file.iter_clients().collect::<Vec<&Client>>()
+    );
+    assert_eq!(ledgered(&memory, 6), ledgered(&file, 6));
+    for tx in (1..=6).map(TxId::Number) {
+        assert_eq!(
+            memory.dispute_ledger.contains(&tx),
+            file.dispute_ledger.contains(&tx)
+        );
+    }
+}
+
+#[test]
+fn journal() {
+    let mut a = Authority::default().with_journal();
+    a.apply_iter(
+        vec![
+            operation(Deposit, 1, 1, d(1)),
+            operation(Deposit, 1, 2, d(2)),
+            operation(Withdrawal, 1, 3, d(2)),
+            operation(Deposit, 2, 4, d(5)),
+            dispute(Dispute, 2, 2),
+            dispute(Resolve, 2, 2),
+            dispute(Dispute, 1, 3),
+            dispute(Chargeback, 1, 3),
+            dispute(Dispute, 2, 4),
+            dispute(Dispute, 1, 1),
+            // Account locked, nothing is posted
+            dispute(Chargeback, 1, 1),
+        ]
+        .into_iter(),
+    );
+
+    assert_eq!(10, a.journal_mut().drain().count());
+    assert_eq!(0, a.journal_mut().drain().count());
+
+    let report = a.trial_balance();
+    assert!(report.is_balanced());
+    assert_eq!(d(24), report.debit());
+
+    let clients = a
+        .iter_clients()
+        .map(|c| (c.id(), *c.available(), *c.held()))
+        .collect::<Vec<_>>();
+    for (id, available, held) in clients {
+        assert_eq!(available, a.journal().balance(Account::ClientAvailable(id)));
+        assert_eq!(held, a.journal().balance(Account::ClientHeld(id)));
+    }
+    assert_eq!(d(-6), a.journal().balance(Account::Settlement));
+    assert_eq!(d(-2), a.journal().balance(Account::ChargebackLoss));
+
+    // Journal moving funds of client 2 into debt without its balances
+    // following still balances, but no longer reconciles
+    a.journal.debt(7.into(), 2, d(1));
+    let report = a.trial_balance();
+    assert_eq!(report.debit(), report.credit());
+    assert!(!report.is_balanced());
+    assert_eq!(
+        vec![Account::ClientAvailable(2), Account::ClientDebt(2)],
+        report
+            .discrepancies()
+            .iter()
+            .map(|d| d.account())
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn verify() {
+    let mut a = Authority::default();
+    a.apply_iter(
+        vec![
+            operation(Deposit, 1, 1, d(1)),
+            operation(Deposit, 1, 2, d(2)),
+            operation(Withdrawal, 1, 3, d(2)),
+            operation(Deposit, 2, 4, d(5)),
+            dispute(Dispute, 2, 2),
+            dispute(Dispute, 1, 3),
+            dispute(Chargeback, 1, 3),
+            dispute(Dispute, 2, 4),
+        ]
+        .into_iter(),
+    );
+    assert_eq!(Vec::<Violation>::new(), a.verify().unwrap());
+
+    a.client_state.insert(1, Client::test(1, 1, 3, 4, true));
+    a.client_state.insert(2, Client::test(2, 1, 5, 5, false));
+    assert_eq!(
+        vec![
+            Violation::Held {
+                client: 1,
+                held: d(3),
+                disputed: d(2),
+            },
+            Violation::Total {
+                client: 1,
+                total: d(4),
+                expected: d(3),
+            },
+            Violation::Balance {
+                client: 2,
+                available: d(1),
+                held: d(5),
+                debt: Decimal::new(0, 4),
+                total: d(5),
+            },
+        ],
+        a.verify().unwrap()
+    );
+}
+
+#[test]
+fn signatures() {
+    let key_1 = SigningKey::from_bytes(&[1; 32]);
+    let key_2 = SigningKey::from_bytes(&[2; 32]);
+
+    let mut keys = KeyRegistry::default();
+    keys.insert(1, key_1.verifying_key());
+    keys.insert(2, key_2.verifying_key());
+
+    let mut a = Authority::default().with_key_registry(keys);
+    a.apply_iter(
+        vec![
+            sign(operation(Deposit, 1, 1, d(2)), &key_1),
+            sign(operation(Deposit, 2, 2, d(2)), &key_2),
+            // Missing signature
+            operation(Deposit, 1, 3, d(1)),
+            // Signed by another client
+            sign(operation(Withdrawal, 1, 4, d(1)), &key_2),
+            // Altered after signing
+            match sign(operation(Withdrawal, 2, 5, d(1)), &key_2) {
+                Transaction::Operation(o) => {
+                    operation(Withdrawal, 2, 5, d(2)).with_signature(*o.signature().unwrap())
+                }
+                t => t,
+            },
+            // Client without a registered key
+            sign(operation(Deposit, 3, 6, d(1)), &key_1),
+            sign(dispute(Dispute, 2, 1), &key_2),
+        ]
+        .into_iter(),
+    );
+
+    assert_eq!(
+        vec![
+            &Client::test(1, 0, 2, 2, false),
+            &Client::test(2, 2, 0, 2, false),
+        ],
+        a.iter_clients().collect::<Vec<&Client>>()
+    );
+
+    // Dispute metadata is signed too
+    let signed = sign(
+        Transaction::Dispute(DisputeTransaction::new(Dispute, 1, 2.into()).with_reason("10.4")),
+        &key_1,
+    );
+    let altered = Transaction::Dispute(
+        DisputeTransaction::new(Dispute, 1, 2.into())
+            .with_reason("13.1")
+            .with_signature(*signed.signature().unwrap()),
+    );
+    assert!(matches!(
+        a.apply(altered),
+        Err(TransactionError::Signature(SignatureError::Invalid(..)))
+    ));
+    a.apply(signed).unwrap();
+
+    // As are wallets
+    let signed = sign(
+        Transaction::Operation(
+            OperationTransaction::new(Withdrawal, 2, 7.into(), d(1)).with_wallet(1),
+        ),
+        &key_2,
+    );
+    let altered = Transaction::Operation(
+        OperationTransaction::new(Withdrawal, 2, 7.into(), d(1))
+            .with_wallet(0)
+            .with_signature(*signed.signature().unwrap()),
+    );
+    assert!(matches!(
+        a.apply(altered),
+        Err(TransactionError::Signature(SignatureError::Invalid(..)))
+    ));
+
+    // As are escrow payees
+    let lock = |payee| {
+        EscrowTransaction::new(EscrowTransactionType::Escrow, 2, 8.into())
+            .with_amount(d(1))
+            .with_counterparty(payee)
+    };
+    let signed = sign(Transaction::Escrow(lock(1)), &key_2);
+    let altered = Transaction::Escrow(lock(3).with_signature(*signed.signature().unwrap()));
+    assert!(matches!(
+        a.apply(altered),
+        Err(TransactionError::Signature(SignatureError::Invalid(..)))
+    ));
+}
+
+#[test]
+fn duplicate() {
+    let mut a = Authority::default();
+    assert_eq!(
+        Outcome::Applied,
+        a.apply(operation(Deposit, 1, 1, d(1))).unwrap()
+    );
+    // Exact resubmission is idempotent
+    assert_eq!(
+        Outcome::Duplicate,
+        a.apply(operation(Deposit, 1, 1, d(1))).unwrap()
+    );
+    // Any other reuse of the tx conflicts
+    for t in [
+        operation(Deposit, 1, 1, d(2)),
+        operation(Withdrawal, 1, 1, d(1)),
+        operation(Deposit, 2, 1, d(1)),
+    ] {
+        assert!(matches!(
+            a.apply(t),
+            Err(TransactionError::Operation(
+                OperationError::TransactionConflict(TxId::Number(1))
+            ))
+        ));
+    }
+
+    assert_eq!(
+        vec![&Client::test(1, 1, 0, 1, false)],
+        a.iter_clients().collect::<Vec<&Client>>()
+    );
+}
+
+#[test]
+fn batch() {
+    let mut a = Authority::default().with_journal();
+    a.apply_iter(
+        vec![
+            operation(Deposit, 1, 1, d(5)),
+            operation(Deposit, 2, 2, d(5)),
+            dispute(Dispute, 2, 2),
+        ]
+        .into_iter(),
+    );
+    a.journal_mut().drain().for_each(drop);
+
+    // Rejected withdrawal rolls back the whole batch
+    let res = a.apply_batch(&[
+        operation(Deposit, 1, 3, d(1)),
+        operation(Deposit, 3, 4, d(1)),
+        dispute(Dispute, 1, 1),
+        dispute(Resolve, 2, 2),
+        operation(Withdrawal, 1, 5, d(10)),
+    ]);
+    assert!(matches!(
+        res,
+        Err(BatchError::Rejected(
+            4,
+            TransactionError::Operation(OperationError::WithdrawExceeded(..))
+        ))
+    ));
+
+    assert_eq!(
+        vec![
+            &Client::test(1, 5, 0, 5, false),
+            &Client::test(2, 0, 5, 5, false),
+        ],
+        a.iter_clients().collect::<Vec<&Client>>()
+    );
+    assert_eq!(vec![1, 2], ledgered(&a, 5));
+    assert!(a.dispute_ledger.contains(&TxId::Number(2)));
+    assert!(!a.dispute_ledger.contains(&TxId::Number(1)));
+    assert_eq!(1, a.iter_dispute_history().count());
+    assert_eq!(0, a.journal_mut().drain().count());
+    assert_eq!(d(-10), a.journal().balance(Account::Settlement));
+    assert_eq!(Vec::<Violation>::new(), a.verify().unwrap());
+
+    // Rolled back transactions may be resubmitted
+    assert_eq!(
+        vec![Outcome::Applied, Outcome::Duplicate, Outcome::Applied],
+        a.apply_batch(&[
+            operation(Deposit, 1, 3, d(1)),
+            operation(Deposit, 1, 3, d(1)),
+            dispute(Resolve, 2, 2),
+        ])
+        .unwrap()
+    );
+    assert_eq!(
+        vec![
+            &Client::test(1, 6, 0, 6, false),
+            &Client::test(2, 5, 0, 5, false),
+        ],
+        a.iter_clients().collect::<Vec<&Client>>()
+    );
+}
+
+#[test]
+fn reversal() {
+    let mut a = Authority::default().with_journal();
+    a.apply_iter(
+        vec![
+            operation(Deposit, 1, 1, d(5)),
+            operation(Deposit, 1, 2, d(3)),
+            operation(Withdrawal, 1, 3, d(4)),
+            operation(Deposit, 2, 4, d(2)),
+            dispute(Dispute, 2, 4),
+        ]
+        .into_iter(),
+    );
+
+    // Reversing a deposit may leave the account negative
+    a.apply(dispute(Reversal, 1, 1)).unwrap();
+    a.apply(dispute(Reversal, 1, 3)).unwrap();
+    assert_eq!(
+        vec![
+            &Client::test(1, 3, 0, 3, false),
+            &Client::test(2, 0, 2, 2, false),
+        ],
+        a.iter_clients().collect::<Vec<&Client>>()
+    );
+    // Reversals undo the transaction in full
+    assert!(matches!(
+        a.apply(Transaction::Dispute(
+            DisputeTransaction::new(Reversal, 1, 2.into()).with_amount(d(1))
+        )),
+        Err(TransactionError::Dispute(DisputeError::InvalidAmount(..)))
+    ));
+    a.apply(dispute(Reversal, 1, 2)).unwrap();
+    assert_eq!(
+        Some(&Client::test(1, 0, 0, 0, false)),
+        a.iter_clients().next()
+    );
+
+    assert!(matches!(
+        a.apply(dispute(Reversal, 1, 1)),
+        Err(TransactionError::Dispute(DisputeError::Reversed(..)))
+    ));
+    assert!(matches!(
+        a.apply(dispute(Dispute, 2, 1)),
+        Err(TransactionError::Dispute(DisputeError::Reversed(..)))
+    ));
+    assert!(matches!(
+        a.apply(dispute(Reversal, 2, 4)),
+        Err(TransactionError::Dispute(DisputeError::DisputeExists(..)))
+    ));
+    a.apply(dispute(Resolve, 2, 4)).unwrap();
+    assert!(matches!(
+        a.apply(dispute(Reversal, 1, 4)),
+        Err(TransactionError::Dispute(DisputeError::ReversalConflict(
+            ..
+        )))
+    ));
+
+    assert_eq!(
+        3,
+        a.iter_dispute_history()
+            .filter(|t| matches!(t.transaction_type(), Reversal))
+            .count()
+    );
+    assert_eq!(d(-2), a.journal().balance(Account::Settlement));
+    assert_eq!(Vec::<Violation>::new(), a.verify().unwrap());
+}
+
+#[test]
+fn partial_dispute() {
+    let partial = |tt, client, tx, amount| match dispute(tt, client, tx) {
+        Transaction::Dispute(t) => Transaction::Dispute(t.with_amount(amount)),
+        t => t,
+    };
+
+    let mut a = Authority::default().with_journal();
+    a.apply_iter(
+        vec![
+            operation(Deposit, 1, 1, d(10)),
+            operation(Deposit, 2, 2, d(10)),
+            operation(Withdrawal, 2, 3, d(4)),
+        ]
+        .into_iter(),
+    );
+
+    // Partial hold, released in full by a resolve, which may only give the
+    // amount held
+    a.apply(partial(Dispute, 1, 1, d(4))).unwrap();
+    assert_eq!(
+        Some(&Client::test(1, 6, 4, 10, false)),
+        a.iter_clients().next()
+    );
+    assert!(matches!(
+        a.apply(partial(Resolve, 1, 1, d(3))),
+        Err(TransactionError::Dispute(DisputeError::InvalidAmount(..)))
+    ));
+    a.apply(partial(Resolve, 1, 1, d(4))).unwrap();
+    assert_eq!(
+        Some(&Client::test(1, 10, 0, 10, false)),
+        a.iter_clients().next()
+    );
+
+    // Partial chargeback of a partial hold releases the rest
+    a.apply(partial(Dispute, 1, 1, d(6))).unwrap();
+    a.apply(partial(Chargeback, 1, 1, d(2))).unwrap();
+    assert_eq!(
+        Some(&Client::test(1, 8, 0, 8, true)),
+        a.iter_clients().next()
+    );
+
+    // Only what hasn't been charged back remains disputable
+    assert!(matches!(
+        a.apply(partial(Dispute, 1, 1, d(9))),
+        Err(TransactionError::Dispute(DisputeError::AmountExceeded(..)))
+    ));
+    assert!(matches!(
+        a.apply(partial(Dispute, 1, 1, d(0))),
+        Err(TransactionError::Dispute(DisputeError::InvalidAmount(..)))
+    ));
+    a.apply(dispute(Dispute, 1, 1)).unwrap();
+    assert_eq!(
+        Some(&Client::test(1, 0, 8, 8, true)),
+        a.iter_clients().next()
+    );
+    a.apply(dispute(Resolve, 1, 1)).unwrap();
+
+    // Chargebacks may not exceed the amount held
+    a.apply(partial(Dispute, 2, 3, d(3))).unwrap();
+    assert!(matches!(
+        a.apply(partial(Chargeback, 2, 3, d(4))),
+        Err(TransactionError::Dispute(DisputeError::AmountExceeded(..)))
+    ));
+    a.apply(partial(Chargeback, 2, 3, d(1))).unwrap();
+    assert_eq!(
+        vec![
+            &Client::test(1, 8, 0, 8, true),
+            &Client::test(2, 7, 0, 7, true),
+        ],
+        a.iter_clients().collect::<Vec<&Client>>()
+    );
+
+    assert_eq!(Vec::<Violation>::new(), a.verify().unwrap());
+    assert!(a.trial_balance().is_balanced());
+}
+
+#[test]
+fn dispute_deadline() {
+    let mut a = Authority::default()
+        .with_dispute_deadline(Deadline::Transactions(2), DeadlinePolicy::Resolve);
+    a.apply_iter(
+        vec![
+            operation(Deposit, 1, 1, d(5)),
+            operation(Deposit, 2, 2, d(5)),
+            dispute(Dispute, 2, 1),
+            operation(Deposit, 1, 3, d(1)),
+            dispute(Dispute, 1, 2),
+        ]
+        .into_iter(),
+    );
+
+    let aging = a.dispute_aging().unwrap();
+    assert_eq!(
+        vec![(TxId::Number(1), 2, d(5), 1), (TxId::Number(2), 1, d(5), 0)],
+        aging
+            .iter()
+            .map(|age| (age.tx(), age.client(), age.amount(), age.transactions()))
+            .collect::<Vec<_>>()
+    );
+
+    // Second transaction ledgered since tx 1 was disputed resolves it
+    a.apply(operation(Deposit, 1, 4, d(1))).unwrap();
+    assert_eq!(
+        vec![TxId::Number(2)],
+        a.dispute_aging()
+            .unwrap()
+            .iter()
+            .map(|age| age.tx())
+            .collect::<Vec<_>>()
+    );
+    let settled = a.iter_dispute_history().last().unwrap();
+    assert!(matches!(settled.transaction_type(), Resolve));
+    assert_eq!(2, settled.client());
+    assert_eq!(
+        vec![
+            &Client::test(1, 7, 0, 7, false),
+            &Client::test(2, 0, 5, 5, false),
+        ],
+        a.iter_clients().collect::<Vec<&Client>>()
+    );
+
+    // Timed deadlines charge back, or resolve disputes which can't be
+    let mut a = Authority::default().with_dispute_deadline(
+        Deadline::Duration(Duration::from_secs(60)),
+        DeadlinePolicy::Chargeback,
+    );
+    let at = |t: Transaction, timestamp| t.with_timestamp(timestamp);
+    a.apply_iter(
+        vec![
+            at(operation(Deposit, 1, 1, d(5)), 0),
+            at(operation(Deposit, 1, 2, d(5)), 0),
+            at(dispute(Dispute, 1, 1), 10),
+            at(dispute(Dispute, 1, 2), 20),
+            at(operation(Deposit, 2, 3, d(1)), 70),
+        ]
+        .into_iter(),
+    );
+    assert_eq!(
+        Some(&Client::test(1, 0, 10, 10, false)),
+        a.iter_clients().next()
+    );
+    assert_eq!(Some(60), a.dispute_aging().unwrap()[0].seconds());
+
+    a.apply(at(operation(Deposit, 2, 4, d(1)), 81)).unwrap();
+    assert_eq!(
+        Some(&Client::test(1, 5, 0, 5, true)),
+        a.iter_clients().next()
+    );
+    assert!(a.dispute_aging().unwrap().is_empty());
+    assert_eq!(Vec::<Violation>::new(), a.verify().unwrap());
+}
+
+#[test]
+fn collections() {
+    let mut a = Authority::default()
+        .with_journal()
+        .with_collections(CollectionPolicy::Share(Decimal::new(5, 1)));
+    a.apply_iter(
+        vec![
+            operation(Deposit, 1, 1, d(10)),
+            operation(Withdrawal, 1, 2, d(8)),
+            dispute(Dispute, 1, 1),
+        ]
+        .into_iter(),
+    );
+
+    // Negative available balance is moved into debt
+    let client = a.iter_clients().next().unwrap().clone();
+    assert!(client.in_collections());
+    assert_eq!(
+        (d(0), d(10), d(8), d(2)),
+        (
+            *client.available(),
+            *client.held(),
+            *client.debt(),
+            *client.total()
+        )
+    );
+    assert_eq!(d(-8), a.journal().balance(Account::ClientDebt(1)));
+
+    // Half of each deposit repays the debt, and funds returned through the
+    // dispute the rest of it
+    a.apply(operation(Deposit, 1, 3, d(4))).unwrap();
+    let client = a.iter_clients().next().unwrap().clone();
+    assert_eq!((d(2), d(6)), (*client.available(), *client.debt()));
+    a.apply(operation(Withdrawal, 1, 4, d(2))).unwrap();
+    a.apply(dispute(Resolve, 1, 1)).unwrap();
+    assert_eq!(
+        Some(&Client::test(1, 4, 0, 4, false)),
+        a.iter_clients().next()
+    );
+
+    a.apply_iter(
+        vec![
+            operation(Deposit, 2, 5, d(5)),
+            operation(Withdrawal, 2, 6, d(5)),
+            dispute(Dispute, 1, 5),
+        ]
+        .into_iter(),
+    );
+    assert!(matches!(
+        a.apply(operation(Withdrawal, 2, 7, d(1))),
+        Err(TransactionError::Operation(
+            OperationError::WithdrawExceeded(..)
+        ))
+    ));
+
+    assert_eq!(
+        vec![(1, d(0), d(8), d(8)), (2, d(5), d(5), d(0))],
+        a.collections()
+            .iter()
+            .map(|c| (c.client(), c.outstanding(), c.incurred(), c.recovered()))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(Vec::<Violation>::new(), a.verify().unwrap());
+    assert!(a.trial_balance().is_balanced());
+}
+
+#[test]
+fn lifecycle() {
+    let mut a = Authority::default().with_strict_accounts();
+    a.set_kyc(2, KycStatus::Verified);
+
+    // Accounts must be opened before use
+    assert!(matches!(
+        a.apply(operation(Deposit, 1, 1, d(10))),
+        Err(TransactionError::Operation(OperationError::UnknownClient(
+            ..
+        )))
+    ));
+    a.apply(account(AccountTransactionType::Open, 1, 1))
+        .unwrap();
+    a.apply(account(AccountTransactionType::Open, 2, 2))
+        .unwrap();
+    assert!(matches!(
+        a.apply(account(AccountTransactionType::Open, 1, 3)),
+        Err(TransactionError::Account(AccountError::AlreadyOpen(1)))
+    ));
+
+    // Account transactions follow the same tx rules as operations
+    assert_eq!(
+        Outcome::Duplicate,
+        a.apply(account(AccountTransactionType::Open, 2, 2))
+            .unwrap()
+    );
+    assert!(matches!(
+        a.apply(account(AccountTransactionType::Close, 2, 2)),
+        Err(TransactionError::Account(
+            AccountError::TransactionConflict(_)
+        ))
+    ));
+    assert!(matches!(
+        a.apply(operation(Deposit, 2, 2, d(1))),
+        Err(TransactionError::Operation(
+            OperationError::TransactionExists(_)
+        ))
+    ));
+
+    // Only verified clients may withdraw
+    a.apply_iter(
+        vec![
+            operation(Deposit, 1, 4, d(10)),
+            operation(Deposit, 2, 5, d(10)),
+        ]
+        .into_iter(),
+    );
+    assert!(matches!(
+        a.apply(operation(Withdrawal, 1, 6, d(10))),
+        Err(TransactionError::Operation(OperationError::Unverified(..)))
+    ));
+    a.apply(operation(Withdrawal, 2, 6, d(10))).unwrap();
+
+    // Accounts party to open disputes or holding funds cannot be closed
+    a.apply(dispute(Dispute, 2, 4)).unwrap();
+    assert!(matches!(
+        a.apply(account(AccountTransactionType::Close, 2, 7)),
+        Err(TransactionError::Account(AccountError::Disputed(2, _)))
+    ));
+    a.apply(dispute(Resolve, 2, 4)).unwrap();
+    assert!(matches!(
+        a.apply(account(AccountTransactionType::Close, 1, 7)),
+        Err(TransactionError::Account(AccountError::Balance(1)))
+    ));
+    a.apply(account(AccountTransactionType::Close, 2, 7))
+        .unwrap();
+
+    // Closed accounts reject operations until reopened
+    assert!(matches!(
+        a.apply(operation(Deposit, 2, 8, d(1))),
+        Err(TransactionError::Operation(OperationError::Closed(2)))
+    ));
+    a.apply(account(AccountTransactionType::Open, 2, 8))
+        .unwrap();
+    a.apply(operation(Deposit, 2, 9, d(1))).unwrap();
+    assert_eq!(
+        vec![Some(KycStatus::Pending), Some(KycStatus::Verified)],
+        a.iter_clients().map(Client::kyc).collect::<Vec<_>>()
+    );
+
+    assert!(matches!(
+        a.apply(account(AccountTransactionType::Open, 3, 9)),
+        Err(TransactionError::Account(AccountError::TransactionExists(
+            _
+        )))
+    ));
+
+    // Clients created before KYC is tracked are gated too
+    let mut a = Authority::default();
+    a.apply(operation(Deposit, 1, 1, d(10))).unwrap();
+    a.set_kyc(2, KycStatus::Verified);
+    assert!(matches!(
+        a.apply(operation(Withdrawal, 1, 2, d(1))),
+        Err(TransactionError::Operation(OperationError::Unverified(..)))
+    ));
+}
+
+#[test]
+fn escrow_settlement() {
+    let mut a = Authority::default();
+    let lock = |tx: u64, amount| {
+        Transaction::Escrow(
+            EscrowTransaction::new(EscrowTransactionType::Escrow, 1, tx.into())
+                .with_amount(amount)
+                .with_counterparty(2),
+        )
+    };
+    a.apply_iter(vec![operation(Deposit, 1, 1, d(10)), lock(2, d(6))].into_iter());
+    assert_eq!(
+        Some(&Client::test(1, 4, 0, 4, false)),
+        a.iter_clients().next()
+    );
+    assert!(matches!(
+        a.apply(lock(3, d(5))),
+        Err(TransactionError::Escrow(EscrowError::Operation(
+            OperationError::WithdrawExceeded(..)
+        )))
+    ));
+
+    // Only the payer may release, and not while disputed
+    assert!(matches!(
+        a.apply(escrow(EscrowTransactionType::Release, 2, 2)),
+        Err(TransactionError::Escrow(EscrowError::Conflict(..)))
+    ));
+    a.apply(dispute(Dispute, 2, 2)).unwrap();
+    assert!(matches!(
+        a.apply(escrow(EscrowTransactionType::Release, 1, 2)),
+        Err(TransactionError::Escrow(EscrowError::Disputed(_)))
+    ));
+    a.apply(dispute(Resolve, 2, 2)).unwrap();
+    a.apply(escrow(EscrowTransactionType::Release, 1, 2))
+        .unwrap();
+    assert!(matches!(
+        a.apply(escrow(EscrowTransactionType::Refund, 2, 2)),
+        Err(TransactionError::Escrow(EscrowError::Settled(_)))
+    ));
+
+    // Chargebacks of disputed escrows refund the payer
+    a.apply_iter(
+        vec![
+            lock(3, d(4)),
+            dispute(Dispute, 1, 3),
+            dispute(Chargeback, 1, 3),
+        ]
+        .into_iter(),
+    );
+
+    assert_eq!(
+        vec![
+            Client::test(1, 4, 0, 4, false),
+            Client::test(2, 6, 0, 6, false)
+        ],
+        a.iter_clients().cloned().collect::<Vec<_>>()
+    );
+    assert_eq!(
+        vec![EscrowStatus::Released, EscrowStatus::Refunded],
+        a.escrows().iter().map(|e| e.status()).collect::<Vec<_>>()
+    );
+    assert_eq!(d(0), a.journal().balance(Account::Escrow));
+    assert!(a.verify().unwrap().is_empty());
+
+    // Escrows count against withdrawal limits
+    let limits = Limits::new(vec![Limit::new(None, Window::Day, None, Some(d(5)))]);
+    let mut a = Authority::default().with_limits(limits);
+    a.apply_iter(vec![operation(Deposit, 1, 1, d(10)), lock(2, d(3))].into_iter());
+    assert!(matches!(
+        a.apply(lock(3, d(3))),
+        Err(TransactionError::Escrow(EscrowError::Operation(
+            OperationError::LimitExceeded(..)
+        )))
+    ));
+    a.apply(escrow(EscrowTransactionType::Release, 1, 2))
+        .unwrap();
+    assert!(matches!(
+        a.apply(operation(Withdrawal, 1, 4, d(3))),
+        Err(TransactionError::Operation(OperationError::LimitExceeded(
+            ..
+        )))
+    ));
+
+    // Locked parties may neither release nor be released or refunded to
+    let mut a = Authority::default();
+    a.apply_iter(
+        vec![
+            operation(Deposit, 1, 1, d(10)),
+            operation(Deposit, 2, 2, d(5)),
+            lock(3, d(4)),
+            Transaction::Escrow(
+                EscrowTransaction::new(EscrowTransactionType::Escrow, 2, 4.into())
+                    .with_amount(d(1))
+                    .with_counterparty(1),
+            ),
+            dispute(Dispute, 1, 1),
+            dispute(Chargeback, 1, 1),
+        ]
+        .into_iter(),
+    );
+    for t in [
+        escrow(EscrowTransactionType::Release, 1, 3),
+        escrow(EscrowTransactionType::Release, 2, 4),
+        escrow(EscrowTransactionType::Refund, 1, 4),
+    ] {
+        assert!(matches!(
+            a.apply(t),
+            Err(TransactionError::Escrow(EscrowError::Operation(
+                OperationError::Locked(1)
+            )))
+        ));
+    }
+
+    // Leaving escrows to be charged back
+    a.apply(dispute(Dispute, 2, 4)).unwrap();
+    a.apply(dispute(Chargeback, 2, 4)).unwrap();
+    assert_eq!(
+        Some(&Client::test(2, 5, 0, 5, false)),
+        a.iter_clients().nth(1)
+    );
+}
@@ -1,5 +1,121 @@
 use rust_decimal::Decimal;
-use serde::Deserialize;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{fmt, str::FromStr};
+
+/// Identifier of a [Client](crate::Client)
+pub type ClientId = u64;
+
+/// Identifier of a [Wallet](crate::Wallet) under a client, where operations
+/// without one apply to the client's default wallet `0`
+pub type WalletId = u16;
+
+/// Identifier of a transaction
+///
+/// Upstream systems either issue sequential numeric ids or UUIDs, both of
+/// which are accepted side by side.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TxId {
+    Number(u64),
+    Uuid(u128),
+}
+
+impl From<u64> for TxId {
+    fn from(tx: u64) -> Self {
+        TxId::Number(tx)
+    }
+}
+
+impl fmt::Display for TxId {
+    /// Formats numeric ids in decimal and UUIDs in their hyphenated form
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TxId::Number(tx) => write!(f, "{}", tx),
+            TxId::Uuid(uuid) => {
+                let hex = format!("{:032x}", uuid);
+                write!(
+                    f,
+                    "{}-{}-{}-{}-{}",
+                    &hex[..8],
+                    &hex[8..12],
+                    &hex[12..16],
+                    &hex[16..20],
+                    &hex[20..]
+                )
+            }
+        }
+    }
+}
+
+impl FromStr for TxId {
+    type Err = String;
+
+    /// Parses a decimal id, or a UUID in either its hyphenated or simple form
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()) {
+            return s
+                .parse()
+                .map(TxId::Number)
+                .map_err(|_| format!("Transaction id {} exceeds maximum of {}", s, u64::MAX));
+        }
+
+        let hyphenated = s.len() == 36
+            && s.char_indices()
+                .all(|(i, c)| matches!(i, 8 | 13 | 18 | 23) == (c == '-'));
+        let simple = if hyphenated {
+            s.replace('-', "")
+        } else {
+            s.to_string()
+        };
+
+        if simple.len() == 32 && simple.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Ok(TxId::Uuid(u128::from_str_radix(&simple, 16).unwrap()));
+        }
+
+        Err(format!("Invalid transaction id: {}", s))
+    }
+}
+
+impl Serialize for TxId {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            TxId::Number(tx) => serializer.serialize_u64(*tx),
+            TxId::Uuid(_) => serializer.collect_str(self),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for TxId {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl de::Visitor<'_> for Visitor {
+            type Value = TxId;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a numeric or UUID transaction id")
+            }
+
+            fn visit_u64<E>(self, tx: u64) -> Result<TxId, E> {
+                Ok(TxId::Number(tx))
+            }
+
+            fn visit_str<E>(self, s: &str) -> Result<TxId, E>
+            where
+                E: de::Error,
+            {
+                s.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
 
 #[derive(Copy, Clone, Debug, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
@@ -10,25 +126,45 @@ pub enum OperationTransactionType {
     Withdrawal,
 }
 
+impl fmt::Display for OperationTransactionType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OperationTransactionType::Deposit => write!(f, "deposit"),
+            OperationTransactionType::Withdrawal => write!(f, "withdrawal"),
+        }
+    }
+}
+
 /// Represents transactions which are entered into the transaction ledger
 /// which can be indexed by their `id`.
 ///
 /// The choice to use [Decimal] for amount is for ease of parsing, however,
 /// a u64 fixed precision type will  
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 #[serde(tag = "type")]
 pub struct OperationTransaction {
     transaction_type: OperationTransactionType,
-    client: u16,
-    tx: u32,
+    client: ClientId,
+    tx: TxId,
     amount: Decimal,
+    timestamp: Option<u64>,
+    #[serde(default)]
+    wallet: WalletId,
+    #[serde(skip)]
+    signature: Option<Box<[u8; 64]>>,
+    /// Whether the transaction has been undone by a reversal
+    #[serde(skip)]
+    reversed: bool,
+    /// Part of the amount which has been charged back
+    #[serde(skip)]
+    charged_back: Decimal,
 }
 
 impl OperationTransaction {
     pub fn new(
         transaction_type: OperationTransactionType,
-        client: u16,
-        tx: u32,
+        client: ClientId,
+        tx: TxId,
         amount: Decimal,
     ) -> Self {
         Self {
@@ -36,24 +172,82 @@ impl OperationTransaction {
             client,
             tx,
             amount,
+            timestamp: None,
+            wallet: 0,
+            signature: None,
+            reversed: false,
+            charged_back: Decimal::ZERO,
         }
     }
 
+    /// Sets the time, in seconds since the unix epoch, the transaction was
+    /// issued at
+    pub fn with_timestamp(mut self, timestamp: u64) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Sets the issuing client's Ed25519 signature over the transaction
+    pub fn with_signature(mut self, signature: [u8; 64]) -> Self {
+        self.signature = Some(Box::new(signature));
+        self
+    }
+
+    /// Sets the wallet of the client the transaction applies to
+    pub fn with_wallet(mut self, wallet: WalletId) -> Self {
+        self.wallet = wallet;
+        self
+    }
+
     pub fn transaction_type(&self) -> OperationTransactionType {
         self.transaction_type
     }
 
-    pub fn client(&self) -> u16 {
+    pub fn client(&self) -> ClientId {
         self.client
     }
 
-    pub fn tx(&self) -> u32 {
+    pub fn tx(&self) -> TxId {
         self.tx
     }
 
     pub fn amount(&self) -> Decimal {
         self.amount
     }
+
+    pub fn timestamp(&self) -> Option<u64> {
+        self.timestamp
+    }
+
+    pub fn signature(&self) -> Option<&[u8; 64]> {
+        self.signature.as_deref()
+    }
+
+    pub fn wallet(&self) -> WalletId {
+        self.wallet
+    }
+
+    pub fn reversed(&self) -> bool {
+        self.reversed
+    }
+
+    pub(crate) fn set_reversed(&mut self) {
+        self.reversed = true;
+    }
+
+    pub fn charged_back(&self) -> Decimal {
+        self.charged_back
+    }
+
+    pub(crate) fn add_charged_back(&mut self, amount: Decimal) {
+        self.charged_back += amount;
+    }
+
+    /// Part of the amount which may still be disputed, being whatever has not
+    /// been charged back
+    pub fn disputable(&self) -> Decimal {
+        self.amount - self.charged_back
+    }
 }
 
 #[derive(Copy, Clone, Debug, Deserialize)]
@@ -65,39 +259,409 @@ pub enum DisputeTransactionType {
     Resolve,
     /// Resolves previously opened dispute via a charge back
     Chargeback,
+    /// Undoes the referenced transaction, which may no longer be disputed
+    Reversal,
+}
+
+impl fmt::Display for DisputeTransactionType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DisputeTransactionType::Dispute => write!(f, "dispute"),
+            DisputeTransactionType::Resolve => write!(f, "resolve"),
+            DisputeTransactionType::Chargeback => write!(f, "chargeback"),
+            DisputeTransactionType::Reversal => write!(f, "reversal"),
+        }
+    }
 }
 
 /// Represents transactions which refer to
 /// [OperationTransactions](OperationTransaction) and change their dispute
 /// state.
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 #[serde(tag = "type")]
 pub struct DisputeTransaction {
     transaction_type: DisputeTransactionType,
-    client: u16,
-    tx: u32,
+    client: ClientId,
+    tx: TxId,
+    /// Part of the disputed transaction's amount to hold or charge back
+    amount: Option<Decimal>,
+    timestamp: Option<u64>,
+    #[serde(skip)]
+    signature: Option<Box<[u8; 64]>>,
+    /// Reason code, such as a card network's, the dispute was raised for
+    reason: Option<String>,
+    /// Free-text reference to evidence backing the dispute
+    reference: Option<String>,
+    /// Number of transactions ledgered when the dispute was opened
+    #[serde(skip)]
+    opened_seq: u64,
+    /// Latest timestamp seen when the dispute was opened
+    #[serde(skip)]
+    opened_at: Option<u64>,
 }
 
 impl DisputeTransaction {
-    pub fn new(transaction_type: DisputeTransactionType, client: u16, tx: u32) -> Self {
+    pub fn new(transaction_type: DisputeTransactionType, client: ClientId, tx: TxId) -> Self {
         Self {
             transaction_type,
             client,
             tx,
+            amount: None,
+            timestamp: None,
+            signature: None,
+            reason: None,
+            reference: None,
+            opened_seq: 0,
+            opened_at: None,
         }
     }
 
+    /// Limits a dispute or chargeback to part of the disputed transaction's
+    /// amount, or states the amount a resolve or reversal acts on
+    pub fn with_amount(mut self, amount: Decimal) -> Self {
+        self.amount = Some(amount);
+        self
+    }
+
+    /// Sets the reason code the dispute was raised for
+    pub fn with_reason<S>(mut self, reason: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.reason = Some(reason.into());
+        self
+    }
+
+    /// Sets a reference to evidence backing the dispute, such as a case
+    /// number
+    pub fn with_reference<S>(mut self, reference: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.reference = Some(reference.into());
+        self
+    }
+
+    /// Sets the time, in seconds since the unix epoch, the transaction was
+    /// issued at
+    pub fn with_timestamp(mut self, timestamp: u64) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Sets the issuing client's Ed25519 signature over the transaction
+    pub fn with_signature(mut self, signature: [u8; 64]) -> Self {
+        self.signature = Some(Box::new(signature));
+        self
+    }
+
     pub fn transaction_type(&self) -> DisputeTransactionType {
         self.transaction_type
     }
 
-    pub fn client(&self) -> u16 {
+    pub fn client(&self) -> ClientId {
+        self.client
+    }
+
+    pub fn tx(&self) -> TxId {
+        self.tx
+    }
+
+    pub fn amount(&self) -> Option<Decimal> {
+        self.amount
+    }
+
+    pub fn timestamp(&self) -> Option<u64> {
+        self.timestamp
+    }
+
+    pub fn signature(&self) -> Option<&[u8; 64]> {
+        self.signature.as_deref()
+    }
+
+    pub fn reason(&self) -> Option<&str> {
+        self.reason.as_deref()
+    }
+
+    pub fn reference(&self) -> Option<&str> {
+        self.reference.as_deref()
+    }
+
+    pub(crate) fn opened_seq(&self) -> u64 {
+        self.opened_seq
+    }
+
+    pub(crate) fn opened_at(&self) -> Option<u64> {
+        self.opened_at
+    }
+
+    pub(crate) fn set_opened(&mut self, seq: u64, at: Option<u64>) {
+        self.opened_seq = seq;
+        self.opened_at = at;
+    }
+}
+
+#[derive(Copy, Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AccountTransactionType {
+    /// Opens a client account, or reopens a closed one
+    Open,
+    /// Closes a client account with no balance and no open disputes
+    Close,
+}
+
+impl fmt::Display for AccountTransactionType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AccountTransactionType::Open => write!(f, "open"),
+            AccountTransactionType::Close => write!(f, "close"),
+        }
+    }
+}
+
+/// Represents transactions which change the lifecycle of a client account
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type")]
+pub struct AccountTransaction {
+    transaction_type: AccountTransactionType,
+    client: ClientId,
+    tx: TxId,
+    timestamp: Option<u64>,
+    #[serde(skip)]
+    signature: Option<Box<[u8; 64]>>,
+}
+
+impl AccountTransaction {
+    pub fn new(transaction_type: AccountTransactionType, client: ClientId, tx: TxId) -> Self {
+        Self {
+            transaction_type,
+            client,
+            tx,
+            timestamp: None,
+            signature: None,
+        }
+    }
+
+    /// Sets the time, in seconds since the unix epoch, the transaction was
+    /// issued at
+    pub fn with_timestamp(mut self, timestamp: u64) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Sets the issuing client's Ed25519 signature over the transaction
+    pub fn with_signature(mut self, signature: [u8; 64]) -> Self {
+        self.signature = Some(Box::new(signature));
+        self
+    }
+
+    pub fn transaction_type(&self) -> AccountTransactionType {
+        self.transaction_type
+    }
+
+    pub fn client(&self) -> ClientId {
+        self.client
+    }
+
+    pub fn tx(&self) -> TxId {
+        self.tx
+    }
+
+    pub fn timestamp(&self) -> Option<u64> {
+        self.timestamp
+    }
+
+    pub fn signature(&self) -> Option<&[u8; 64]> {
+        self.signature.as_deref()
+    }
+}
+
+/// Represents internal moves of funds between two wallets of a client
+#[derive(Clone, Debug, Deserialize)]
+pub struct TransferTransaction {
+    client: ClientId,
+    tx: TxId,
+    amount: Decimal,
+    from: WalletId,
+    to: WalletId,
+    timestamp: Option<u64>,
+    #[serde(skip)]
+    signature: Option<Box<[u8; 64]>>,
+}
+
+impl TransferTransaction {
+    pub fn new(client: ClientId, tx: TxId, amount: Decimal, from: WalletId, to: WalletId) -> Self {
+        Self {
+            client,
+            tx,
+            amount,
+            from,
+            to,
+            timestamp: None,
+            signature: None,
+        }
+    }
+
+    /// Sets the time, in seconds since the unix epoch, the transaction was
+    /// issued at
+    pub fn with_timestamp(mut self, timestamp: u64) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Sets the issuing client's Ed25519 signature over the transaction
+    pub fn with_signature(mut self, signature: [u8; 64]) -> Self {
+        self.signature = Some(Box::new(signature));
+        self
+    }
+
+    pub fn client(&self) -> ClientId {
+        self.client
+    }
+
+    pub fn tx(&self) -> TxId {
+        self.tx
+    }
+
+    pub fn amount(&self) -> Decimal {
+        self.amount
+    }
+
+    /// Wallet the funds are moved out of
+    pub fn from(&self) -> WalletId {
+        self.from
+    }
+
+    /// Wallet the funds are moved into
+    pub fn to(&self) -> WalletId {
+        self.to
+    }
+
+    pub fn timestamp(&self) -> Option<u64> {
+        self.timestamp
+    }
+
+    pub fn signature(&self) -> Option<&[u8; 64]> {
+        self.signature.as_deref()
+    }
+}
+
+#[derive(Copy, Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EscrowTransactionType {
+    /// Locks funds of the payer in escrow for a counterparty
+    Escrow,
+    /// Credits escrowed funds to the counterparty, on the payer's
+    /// confirmation
+    Release,
+    /// Returns escrowed funds to the payer, on the counterparty's behalf
+    Refund,
+}
+
+impl fmt::Display for EscrowTransactionType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EscrowTransactionType::Escrow => write!(f, "escrow"),
+            EscrowTransactionType::Release => write!(f, "release"),
+            EscrowTransactionType::Refund => write!(f, "refund"),
+        }
+    }
+}
+
+/// Represents transactions which lock funds between two clients until either
+/// released to the counterparty or refunded to the payer
+///
+/// Releases and refunds refer to the escrow by its tx.
+#[derive(Clone, Debug, Deserialize)]
+pub struct EscrowTransaction {
+    transaction_type: EscrowTransactionType,
+    client: ClientId,
+    tx: TxId,
+    amount: Option<Decimal>,
+    counterparty: Option<ClientId>,
+    #[serde(default)]
+    wallet: WalletId,
+    timestamp: Option<u64>,
+    #[serde(skip)]
+    signature: Option<Box<[u8; 64]>>,
+}
+
+impl EscrowTransaction {
+    pub fn new(transaction_type: EscrowTransactionType, client: ClientId, tx: TxId) -> Self {
+        Self {
+            transaction_type,
+            client,
+            tx,
+            amount: None,
+            counterparty: None,
+            wallet: 0,
+            timestamp: None,
+            signature: None,
+        }
+    }
+
+    /// Sets the amount locked by an escrow
+    pub fn with_amount(mut self, amount: Decimal) -> Self {
+        self.amount = Some(amount);
+        self
+    }
+
+    /// Sets the client an escrow may be released to
+    pub fn with_counterparty(mut self, counterparty: ClientId) -> Self {
+        self.counterparty = Some(counterparty);
+        self
+    }
+
+    /// Sets the wallet of the payer an escrow locks funds from
+    pub fn with_wallet(mut self, wallet: WalletId) -> Self {
+        self.wallet = wallet;
+        self
+    }
+
+    /// Sets the time, in seconds since the unix epoch, the transaction was
+    /// issued at
+    pub fn with_timestamp(mut self, timestamp: u64) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Sets the issuing client's Ed25519 signature over the transaction
+    pub fn with_signature(mut self, signature: [u8; 64]) -> Self {
+        self.signature = Some(Box::new(signature));
+        self
+    }
+
+    pub fn transaction_type(&self) -> EscrowTransactionType {
+        self.transaction_type
+    }
+
+    pub fn client(&self) -> ClientId {
         self.client
     }
 
-    pub fn tx(&self) -> u32 {
+    pub fn tx(&self) -> TxId {
         self.tx
     }
+
+    pub fn amount(&self) -> Option<Decimal> {
+        self.amount
+    }
+
+    pub fn counterparty(&self) -> Option<ClientId> {
+        self.counterparty
+    }
+
+    pub fn wallet(&self) -> WalletId {
+        self.wallet
+    }
+
+    pub fn timestamp(&self) -> Option<u64> {
+        self.timestamp
+    }
+
+    pub fn signature(&self) -> Option<&[u8; 64]> {
+        self.signature.as_deref()
+    }
 }
 
 /// Normalized representation of possible transactions
@@ -106,9 +670,162 @@ impl DisputeTransaction {
 /// necessary data is available once the transaction must be processed. This is
 /// contrary to [RawTransaction](crate::transcode::RawTransaction) which has an
 /// optional `amount` property.
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 #[serde(untagged)]
 pub enum Transaction {
     Operation(OperationTransaction),
     Dispute(DisputeTransaction),
+    Account(AccountTransaction),
+    Transfer(TransferTransaction),
+    Escrow(EscrowTransaction),
+}
+
+impl Transaction {
+    /// Sets the time, in seconds since the unix epoch, the transaction was
+    /// issued at
+    pub fn with_timestamp(self, timestamp: u64) -> Self {
+        match self {
+            Transaction::Operation(o) => Transaction::Operation(o.with_timestamp(timestamp)),
+            Transaction::Dispute(d) => Transaction::Dispute(d.with_timestamp(timestamp)),
+            Transaction::Account(a) => Transaction::Account(a.with_timestamp(timestamp)),
+            Transaction::Transfer(t) => Transaction::Transfer(t.with_timestamp(timestamp)),
+            Transaction::Escrow(e) => Transaction::Escrow(e.with_timestamp(timestamp)),
+        }
+    }
+
+    /// Sets the issuing client's Ed25519 signature over the transaction
+    pub fn with_signature(self, signature: [u8; 64]) -> Self {
+        match self {
+            Transaction::Operation(o) => Transaction::Operation(o.with_signature(signature)),
+            Transaction::Dispute(d) => Transaction::Dispute(d.with_signature(signature)),
+            Transaction::Account(a) => Transaction::Account(a.with_signature(signature)),
+            Transaction::Transfer(t) => Transaction::Transfer(t.with_signature(signature)),
+            Transaction::Escrow(e) => Transaction::Escrow(e.with_signature(signature)),
+        }
+    }
+
+    /// Client issuing the transaction
+    pub fn client(&self) -> ClientId {
+        match self {
+            Transaction::Operation(o) => o.client(),
+            Transaction::Dispute(d) => d.client(),
+            Transaction::Account(a) => a.client(),
+            Transaction::Transfer(t) => t.client(),
+            Transaction::Escrow(e) => e.client(),
+        }
+    }
+
+    pub fn tx(&self) -> TxId {
+        match self {
+            Transaction::Operation(o) => o.tx(),
+            Transaction::Dispute(d) => d.tx(),
+            Transaction::Account(a) => a.tx(),
+            Transaction::Transfer(t) => t.tx(),
+            Transaction::Escrow(e) => e.tx(),
+        }
+    }
+
+    /// Amount of operation, transfer and escrow transactions, or given on
+    /// dispute transactions
+    pub fn amount(&self) -> Option<Decimal> {
+        match self {
+            Transaction::Operation(o) => Some(o.amount()),
+            Transaction::Dispute(d) => d.amount(),
+            Transaction::Account(_) => None,
+            Transaction::Transfer(t) => Some(t.amount()),
+            Transaction::Escrow(e) => e.amount(),
+        }
+    }
+
+    pub fn timestamp(&self) -> Option<u64> {
+        match self {
+            Transaction::Operation(o) => o.timestamp(),
+            Transaction::Dispute(d) => d.timestamp(),
+            Transaction::Account(a) => a.timestamp(),
+            Transaction::Transfer(t) => t.timestamp(),
+            Transaction::Escrow(e) => e.timestamp(),
+        }
+    }
+
+    /// Header of the canonical row, which covers every field but the
+    /// signature
+    pub const FIELDS: [&'static str; 10] = [
+        "type",
+        "client",
+        "tx",
+        "amount",
+        "timestamp",
+        "reason",
+        "reference",
+        "wallet",
+        "to_wallet",
+        "counterparty",
+    ];
+
+    /// Values of the canonical row, in the order of
+    /// [FIELDS](Transaction::FIELDS), where absent fields are left empty
+    pub fn fields(&self) -> [String; 10] {
+        let transaction_type = match self {
+            Transaction::Operation(o) => o.transaction_type().to_string(),
+            Transaction::Dispute(d) => d.transaction_type().to_string(),
+            Transaction::Account(a) => a.transaction_type().to_string(),
+            Transaction::Transfer(_) => "transfer".to_string(),
+            Transaction::Escrow(e) => e.transaction_type().to_string(),
+        };
+        let (reason, reference) = match self {
+            Transaction::Dispute(d) => (d.reason(), d.reference()),
+            _ => (None, None),
+        };
+        let (wallet, to_wallet) = match self {
+            Transaction::Operation(o) => (Some(o.wallet()), None),
+            Transaction::Transfer(t) => (Some(t.from()), Some(t.to())),
+            Transaction::Escrow(e) => (Some(e.wallet()), None),
+            _ => (None, None),
+        };
+        let counterparty = match self {
+            Transaction::Escrow(e) => e.counterparty(),
+            _ => None,
+        };
+
+        [
+            transaction_type,
+            self.client().to_string(),
+            self.tx().to_string(),
+            self.amount().map(|a| a.to_string()).unwrap_or_default(),
+            self.timestamp().map(|t| t.to_string()).unwrap_or_default(),
+            reason.unwrap_or_default().to_string(),
+            reference.unwrap_or_default().to_string(),
+            wallet.map(|w| w.to_string()).unwrap_or_default(),
+            to_wallet.map(|w| w.to_string()).unwrap_or_default(),
+            counterparty.map(|c| c.to_string()).unwrap_or_default(),
+        ]
+    }
+
+    pub fn signature(&self) -> Option<&[u8; 64]> {
+        match self {
+            Transaction::Operation(o) => o.signature(),
+            Transaction::Dispute(d) => d.signature(),
+            Transaction::Account(a) => a.signature(),
+            Transaction::Transfer(t) => t.signature(),
+            Transaction::Escrow(e) => e.signature(),
+        }
+    }
+}
+
+impl fmt::Display for Transaction {
+    /// Formats the transaction as its canonical row, of the values of
+    /// [FIELDS](Transaction::FIELDS) quoted as in CSV where need be
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, field) in self.fields().iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            if field.contains([',', '"', '\n', '\r']) {
+                write!(f, "\"{}\"", field.replace('"', "\"\""))?;
+            } else {
+                write!(f, "{}", field)?;
+            }
+        }
+        Ok(())
+    }
 }
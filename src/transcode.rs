@@ -1,78 +1,408 @@
 use crate::{
-    transaction::OperationTransactionType, DisputeTransaction, DisputeTransactionType,
-    OperationTransaction, Transaction,
+    transaction::OperationTransactionType, AccountTransaction, AccountTransactionType, ClientId,
+    ColumnMapping, DisputeTransaction, DisputeTransactionType, EscrowTransaction,
+    EscrowTransactionType, Field, MappingError, OperationTransaction, Transaction,
+    TransferTransaction, TxId, WalletId,
 };
-use csv::Reader;
+use csv::{Reader, StringRecord};
 use rust_decimal::Decimal;
 use serde::Deserialize;
-use std::io::Read;
+use std::{io::Read, num::IntErrorKind, str::FromStr};
 
-#[derive(Deserialize)]
-#[serde(rename_all = "lowercase")]
 enum TransactionType {
     Deposit,
     Withdrawal,
     Dispute,
     Resolve,
     Chargeback,
+    Reversal,
+    Open,
+    Close,
+    Transfer,
+    Escrow,
+    Release,
+    Refund,
+}
+
+impl FromStr for TransactionType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "deposit" => Ok(TransactionType::Deposit),
+            "withdrawal" => Ok(TransactionType::Withdrawal),
+            "dispute" => Ok(TransactionType::Dispute),
+            "resolve" => Ok(TransactionType::Resolve),
+            "chargeback" => Ok(TransactionType::Chargeback),
+            "reversal" => Ok(TransactionType::Reversal),
+            "open" => Ok(TransactionType::Open),
+            "close" => Ok(TransactionType::Close),
+            "transfer" => Ok(TransactionType::Transfer),
+            "escrow" => Ok(TransactionType::Escrow),
+            "release" => Ok(TransactionType::Release),
+            "refund" => Ok(TransactionType::Refund),
+            _ => Err(format!("Unknown transaction type: {}", s)),
+        }
+    }
 }
 
 /// Represents the raw serde validated data entering the program
 ///
 /// Optional `amount` property is used in order to accept csv files which might
-/// be formatted with variable row lengths.
+/// be formatted with variable row lengths. Similarly the `timestamp` column,
+/// in seconds since the unix epoch, hex encoded `signature` column, and the
+/// `reason` and `reference` columns of disputes may be omitted entirely, as
+/// may the `wallet` of operations, the `to_wallet` of transfers and the
+/// `counterparty` of escrows.
+///
+/// Fields are kept as text until conversion, such that malformed values are
+/// reported along with the field they were found in.
 #[derive(Deserialize)]
 struct RawTransaction {
     #[serde(alias = "type")]
-    transaction_type: TransactionType,
-    client: u16,
-    tx: u32,
-    amount: Option<Decimal>,
+    transaction_type: String,
+    client: String,
+    tx: String,
+    amount: Option<String>,
+    timestamp: Option<String>,
+    signature: Option<String>,
+    reason: Option<String>,
+    reference: Option<String>,
+    wallet: Option<String>,
+    to_wallet: Option<String>,
+    counterparty: Option<String>,
+}
+
+fn parse_client(client: &str) -> Result<ClientId, String> {
+    client
+        .parse()
+        .map_err(|e: std::num::ParseIntError| match e.kind() {
+            IntErrorKind::PosOverflow => {
+                format!("Client id {} exceeds maximum of {}", client, ClientId::MAX)
+            }
+            _ => format!("Invalid client id: {}", client),
+        })
+}
+
+fn parse_wallet(wallet: &str) -> Result<WalletId, String> {
+    wallet
+        .parse()
+        .map_err(|_| format!("Invalid wallet id: {}", wallet))
+}
+
+fn parse_amount(amount: &str) -> Result<Decimal, String> {
+    Decimal::from_str(amount)
+        .or_else(|_| Decimal::from_scientific(amount))
+        .map_err(|_| format!("Invalid amount: {}", amount))
+}
+
+/// Conversion error of a single field of a [RawTransaction]
+struct Invalid {
+    field: Field,
+    message: String,
+}
+
+impl Invalid {
+    fn new(field: Field, message: String) -> Self {
+        Self { field, message }
+    }
 }
 
 impl TryFrom<RawTransaction> for Transaction {
-    type Error = String;
+    type Error = Invalid;
 
     fn try_from(transaction: RawTransaction) -> Result<Self, Self::Error> {
-        let client = transaction.client;
-        let tx = transaction.tx;
-        let amount = transaction.amount;
+        let transaction_type = transaction
+            .transaction_type
+            .parse::<TransactionType>()
+            .map_err(|e| Invalid::new(Field::Type, e))?;
+        let client =
+            parse_client(&transaction.client).map_err(|e| Invalid::new(Field::Client, e))?;
+        let tx = transaction
+            .tx
+            .parse::<TxId>()
+            .map_err(|e| Invalid::new(Field::Tx, e))?;
+        let amount = transaction
+            .amount
+            .as_deref()
+            .map(|amount| {
+                let mut amount = parse_amount(amount)?;
+                amount.rescale(4);
+                Ok(amount)
+            })
+            .transpose()
+            .map_err(|e| Invalid::new(Field::Amount, e))?;
+        let timestamp = transaction
+            .timestamp
+            .as_deref()
+            .map(|timestamp| {
+                timestamp
+                    .parse::<u64>()
+                    .map_err(|_| format!("Invalid timestamp: {}", timestamp))
+            })
+            .transpose()
+            .map_err(|e| Invalid::new(Field::Timestamp, e))?;
+        let wallet = transaction
+            .wallet
+            .as_deref()
+            .map(parse_wallet)
+            .transpose()
+            .map_err(|e| Invalid::new(Field::Wallet, e))?
+            .unwrap_or_default();
 
-        let res = match transaction.transaction_type {
+        let res = match transaction_type {
             TransactionType::Deposit => Transaction::Operation({
-                let mut amount =
-                    amount.ok_or_else(|| "Missing amount for deposit operation".to_string())?;
-                amount.rescale(4);
+                let amount = amount.ok_or_else(|| {
+                    Invalid::new(
+                        Field::Amount,
+                        "Missing amount for deposit operation".to_string(),
+                    )
+                })?;
 
                 OperationTransaction::new(OperationTransactionType::Deposit, client, tx, amount)
+                    .with_wallet(wallet)
             }),
             TransactionType::Withdrawal => Transaction::Operation({
-                let mut amount =
-                    amount.ok_or_else(|| "Missing amount for deposit operation".to_string())?;
-                amount.rescale(4);
+                let amount = amount.ok_or_else(|| {
+                    Invalid::new(
+                        Field::Amount,
+                        "Missing amount for withdrawal operation".to_string(),
+                    )
+                })?;
 
                 OperationTransaction::new(OperationTransactionType::Withdrawal, client, tx, amount)
+                    .with_wallet(wallet)
+            }),
+            TransactionType::Dispute => Transaction::Dispute({
+                let t = DisputeTransaction::new(DisputeTransactionType::Dispute, client, tx);
+                match amount {
+                    Some(amount) => t.with_amount(amount),
+                    None => t,
+                }
             }),
-            TransactionType::Dispute => Transaction::Dispute(DisputeTransaction::new(
-                DisputeTransactionType::Dispute,
+            TransactionType::Resolve => Transaction::Dispute({
+                let t = DisputeTransaction::new(DisputeTransactionType::Resolve, client, tx);
+                match amount {
+                    Some(amount) => t.with_amount(amount),
+                    None => t,
+                }
+            }),
+            TransactionType::Chargeback => Transaction::Dispute({
+                let t = DisputeTransaction::new(DisputeTransactionType::Chargeback, client, tx);
+                match amount {
+                    Some(amount) => t.with_amount(amount),
+                    None => t,
+                }
+            }),
+            TransactionType::Reversal => Transaction::Dispute({
+                let t = DisputeTransaction::new(DisputeTransactionType::Reversal, client, tx);
+                match amount {
+                    Some(amount) => t.with_amount(amount),
+                    None => t,
+                }
+            }),
+            TransactionType::Open => Transaction::Account(AccountTransaction::new(
+                AccountTransactionType::Open,
                 client,
                 tx,
             )),
-            TransactionType::Resolve => Transaction::Dispute(DisputeTransaction::new(
-                DisputeTransactionType::Resolve,
+            TransactionType::Close => Transaction::Account(AccountTransaction::new(
+                AccountTransactionType::Close,
                 client,
                 tx,
             )),
-            TransactionType::Chargeback => Transaction::Dispute(DisputeTransaction::new(
-                DisputeTransactionType::Chargeback,
+            TransactionType::Transfer => Transaction::Transfer({
+                let amount = amount.ok_or_else(|| {
+                    Invalid::new(Field::Amount, "Missing amount for transfer".to_string())
+                })?;
+                let to = transaction
+                    .to_wallet
+                    .as_deref()
+                    .ok_or_else(|| "Missing destination wallet for transfer".to_string())
+                    .and_then(parse_wallet)
+                    .map_err(|e| Invalid::new(Field::ToWallet, e))?;
+
+                TransferTransaction::new(client, tx, amount, wallet, to)
+            }),
+            TransactionType::Escrow => Transaction::Escrow({
+                let amount = amount.ok_or_else(|| {
+                    Invalid::new(Field::Amount, "Missing amount for escrow".to_string())
+                })?;
+                let counterparty = transaction
+                    .counterparty
+                    .as_deref()
+                    .ok_or_else(|| "Missing counterparty for escrow".to_string())
+                    .and_then(parse_client)
+                    .map_err(|e| Invalid::new(Field::Counterparty, e))?;
+
+                EscrowTransaction::new(EscrowTransactionType::Escrow, client, tx)
+                    .with_amount(amount)
+                    .with_counterparty(counterparty)
+                    .with_wallet(wallet)
+            }),
+            TransactionType::Release => Transaction::Escrow(EscrowTransaction::new(
+                EscrowTransactionType::Release,
                 client,
                 tx,
             )),
+            TransactionType::Refund => Transaction::Escrow(EscrowTransaction::new(
+                EscrowTransactionType::Refund,
+                client,
+                tx,
+            )),
+        };
+
+        let res = match timestamp {
+            Some(timestamp) => res.with_timestamp(timestamp),
+            None => res,
         };
-        Ok(res)
+
+        // Dispute metadata is ignored on operations
+        let res = match res {
+            Transaction::Dispute(mut d) => {
+                if let Some(reason) = transaction.reason {
+                    d = d.with_reason(reason);
+                }
+                if let Some(reference) = transaction.reference {
+                    d = d.with_reference(reference);
+                }
+                Transaction::Dispute(d)
+            }
+            res => res,
+        };
+
+        Ok(match transaction.signature {
+            Some(signature) => {
+                let mut bytes = [0; 64];
+                hex::decode_to_slice(signature, &mut bytes).map_err(|e| {
+                    Invalid::new(
+                        Field::Signature,
+                        format!("Invalid signature for tx: {}: {}", tx, e),
+                    )
+                })?;
+                res.with_signature(bytes)
+            }
+            None => res,
+        })
     }
 }
 
+/// Error of a row of input, located by the row's byte offset and line, and
+/// the offending column where known
+#[derive(thiserror::Error, Clone, Debug, PartialEq, Eq)]
+#[error("{}", self.describe())]
+pub struct TranscodeError {
+    byte: u64,
+    line: u64,
+    column: Option<usize>,
+    message: String,
+}
+
+impl TranscodeError {
+    /// Byte offset of the start of the row
+    pub fn byte(&self) -> u64 {
+        self.byte
+    }
+
+    pub fn line(&self) -> u64 {
+        self.line
+    }
+
+    /// One based index of the offending column
+    pub fn column(&self) -> Option<usize> {
+        self.column
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    fn describe(&self) -> String {
+        match self.column {
+            Some(column) => format!(
+                "line {}, column {} (byte {}): {}",
+                self.line, column, self.byte, self.message
+            ),
+            None => format!("line {} (byte {}): {}", self.line, self.byte, self.message),
+        }
+    }
+}
+
+/// Column index and description of a csv error, where `columns` maps the
+/// index of each deserialized field back to its input column
+fn describe_csv(e: &csv::Error, columns: &[Option<usize>]) -> (Option<usize>, String) {
+    match e.kind() {
+        csv::ErrorKind::Deserialize { err, .. } => (
+            err.field()
+                .and_then(|field| columns.get(field as usize).copied().flatten()),
+            err.kind().to_string(),
+        ),
+        csv::ErrorKind::Utf8 { err, .. } => (Some(err.field()), err.to_string()),
+        csv::ErrorKind::UnequalLengths {
+            expected_len, len, ..
+        } => (
+            None,
+            format!(
+                "found record with {} fields, but the previous record has {} fields",
+                len, expected_len
+            ),
+        ),
+        _ => (None, e.to_string()),
+    }
+}
+
+/// Produce an iterator of [Transactions](Transaction), or the error of each
+/// row which could not be read, along with the line each was read from, from
+/// input laid out according to `mapping`
+///
+/// Unlike [transcode], errors are left to the caller, such as to stop at the
+/// first error.
+pub fn try_transcode<T>(
+    mut rdr: Reader<T>,
+    mapping: &ColumnMapping,
+) -> Result<impl Iterator<Item = Result<(u64, Transaction), TranscodeError>>, MappingError>
+where
+    T: Read,
+{
+    // Input column of each field, in the order of [Field::ALL]
+    let columns = mapping.resolve(rdr.headers()?)?;
+    let headers = StringRecord::from(Field::ALL.map(|field| field.to_string()).to_vec());
+    let mut record = StringRecord::new();
+
+    Ok(std::iter::from_fn(move || {
+        let (position, res) = match rdr.read_record(&mut record) {
+            Ok(false) => return None,
+            Ok(true) => {
+                let fields = columns.map(|column| column.and_then(|i| record.get(i)).unwrap_or(""));
+                let mapped = StringRecord::from(fields.to_vec());
+
+                let res = mapped
+                    .deserialize::<RawTransaction>(Some(&headers))
+                    .map_err(|e| describe_csv(&e, &columns))
+                    .and_then(|raw| {
+                        Transaction::try_from(raw)
+                            .map_err(|e| (columns[e.field as usize], e.message))
+                    });
+                (record.position().cloned().unwrap(), res)
+            }
+            Err(e) => {
+                let position = e.position().unwrap_or(rdr.position()).clone();
+                (position, Err(describe_csv(&e, &[])))
+            }
+        };
+
+        Some(
+            res.map(|t| (position.line(), t))
+                .map_err(|(column, message)| TranscodeError {
+                    byte: position.byte(),
+                    line: position.line(),
+                    column: column.map(|c| c + 1),
+                    message,
+                }),
+        )
+    }))
+}
+
 /// Produce an iterator of [Transactions](Transaction)
 pub fn transcode<T>(rdr: Reader<T>) -> impl IntoIterator<Item = Transaction>
 where
@@ -86,18 +416,49 @@ where
     //
     // Of course an alternative is implementing [Deserialize] ourselves, but
     // for the purpose of this work it should be enough.
-    rdr.into_deserialize::<RawTransaction>()
-        .filter_map(|r| match r {
-            Ok(rt) => match Transaction::try_from(rt) {
-                Ok(t) => Some(t),
-                Err(e) => {
-                    eprintln!("{}", e);
-                    None
-                }
-            },
-            Err(e) => {
-                eprintln!("{}", e);
-                None
-            }
-        })
+    let rows = try_transcode(rdr, &ColumnMapping::default())
+        .map_err(|e| eprintln!("{}", e))
+        .ok();
+
+    rows.into_iter()
+        .flatten()
+        .filter_map(|r| r.map_err(|e| eprintln!("{}", e)).ok())
+        .map(|(_, t)| t)
+}
+
+/// Produce an iterator of [Transactions](Transaction) from input whose
+/// columns are laid out according to `mapping`
+///
+/// Header-less input must be read with
+/// [has_headers](csv::ReaderBuilder::has_headers) disabled and mapped by
+/// index.
+pub fn transcode_mapped<T>(
+    rdr: Reader<T>,
+    mapping: &ColumnMapping,
+) -> Result<impl IntoIterator<Item = Transaction>, MappingError>
+where
+    T: Read,
+{
+    Ok(try_transcode(rdr, mapping)?
+        .filter_map(|r| r.map_err(|e| eprintln!("{}", e)).ok())
+        .map(|(_, t)| t))
+}
+
+/// Produce an iterator of [Transactions](Transaction), along with the line
+/// each was read from, from input laid out according to `mapping`
+///
+/// Errors are reported to stderr prefixed by the `source` of the offending
+/// row.
+pub fn transcode_lines<T>(
+    rdr: Reader<T>,
+    mapping: &ColumnMapping,
+    source: &str,
+) -> Result<impl Iterator<Item = (u64, Transaction)>, MappingError>
+where
+    T: Read,
+{
+    let source = source.to_string();
+
+    Ok(try_transcode(rdr, mapping)?
+        .filter_map(move |r| r.map_err(|e| eprintln!("{}: {}", source, e)).ok()))
 }
@@ -0,0 +1,170 @@
+use crate::{Account, Authority, ClientId, EscrowStatus, TxId};
+use rust_decimal::Decimal;
+use std::{collections::BTreeMap, fmt, io};
+
+/// Breach of a ledger invariant
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Violation {
+    /// `available + held - debt` does not equal `total`
+    Balance {
+        client: ClientId,
+        available: Decimal,
+        held: Decimal,
+        debt: Decimal,
+        total: Decimal,
+    },
+    /// `held` does not equal the sum of the client's open disputed amounts
+    Held {
+        client: ClientId,
+        held: Decimal,
+        disputed: Decimal,
+    },
+    /// `total` does not equal the net of the client's accepted operations,
+    /// disputes and chargebacks as posted to the journal
+    Total {
+        client: ClientId,
+        total: Decimal,
+        expected: Decimal,
+    },
+    /// The balances of the client's wallets don't sum to its own balances
+    Wallets { client: ClientId },
+    /// The escrow account as posted to the journal does not equal the sum
+    /// of open escrows
+    Escrow { balance: Decimal, escrowed: Decimal },
+    /// An open dispute refers to a transaction missing from the ledger
+    MissingTransaction { tx: TxId },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::Balance {
+                client,
+                available,
+                held,
+                debt,
+                total,
+            } => write!(
+                f,
+                "Client {} available: {} + held: {} - debt: {} doesn't equal total: {}",
+                client, available, held, debt, total
+            ),
+            Violation::Held {
+                client,
+                held,
+                disputed,
+            } => write!(
+                f,
+                "Client {} held: {} doesn't equal disputed: {}",
+                client, held, disputed
+            ),
+            Violation::Total {
+                client,
+                total,
+                expected,
+            } => write!(
+                f,
+                "Client {} total: {} doesn't equal journal total: {}",
+                client, total, expected
+            ),
+            Violation::Wallets { client } => {
+                write!(f, "Client {} wallets don't sum to its balances", client)
+            }
+            Violation::Escrow { balance, escrowed } => write!(
+                f,
+                "Escrow balance: {} doesn't equal escrowed: {}",
+                balance, escrowed
+            ),
+            Violation::MissingTransaction { tx } => {
+                write!(f, "Disputed transaction with tx: {} doesn't exist", tx)
+            }
+        }
+    }
+}
+
+impl Authority {
+    /// Checks every ledger invariant, returning all violations found
+    pub fn verify(&self) -> io::Result<Vec<Violation>> {
+        let mut violations = vec![];
+
+        // Sum of open disputed amounts by client
+        let mut disputed = BTreeMap::<ClientId, Decimal>::new();
+        for tx in self.dispute_ledger.keys() {
+            match self.transaction_ledger.get(tx)? {
+                Some(t) => {
+                    let amount = match self.dispute_ledger.get(tx)? {
+                        Some(d) => d.amount().unwrap_or(t.amount()),
+                        None => t.amount(),
+                    };
+                    *disputed.entry(t.client()).or_default() += amount;
+                }
+                // Disputed escrows hold no client funds
+                None if self.escrows.contains_key(tx) => {}
+                None => violations.push(Violation::MissingTransaction { tx: *tx }),
+            }
+        }
+
+        for client in self.client_state.values() {
+            let (available, held, debt, total) = (
+                *client.available(),
+                *client.held(),
+                *client.debt(),
+                *client.total(),
+            );
+
+            if available + held - debt != total {
+                violations.push(Violation::Balance {
+                    client: client.id(),
+                    available,
+                    held,
+                    debt,
+                    total,
+                });
+            }
+
+            let sums = client
+                .wallets()
+                .fold([Decimal::ZERO; 4], |[a, h, d, t], w| {
+                    [a + w.available(), h + w.held(), d + w.debt(), t + w.total()]
+                });
+            if sums != [available, held, debt, total] {
+                violations.push(Violation::Wallets {
+                    client: client.id(),
+                });
+            }
+
+            let disputed = disputed.get(&client.id()).copied().unwrap_or_default();
+            if held != disputed {
+                violations.push(Violation::Held {
+                    client: client.id(),
+                    held,
+                    disputed,
+                });
+            }
+
+            let expected = self.journal.balance(Account::ClientAvailable(client.id()))
+                + self.journal.balance(Account::ClientHeld(client.id()))
+                + self.journal.balance(Account::ClientDebt(client.id()));
+            if total != expected {
+                violations.push(Violation::Total {
+                    client: client.id(),
+                    total,
+                    expected,
+                });
+            }
+        }
+
+        let balance = self.journal.balance(Account::Escrow);
+        let escrowed = self
+            .escrows
+            .values()
+            .filter(|e| e.status() == EscrowStatus::Open)
+            .map(|e| e.amount())
+            .sum();
+        if balance != escrowed {
+            violations.push(Violation::Escrow { balance, escrowed });
+        }
+
+        Ok(violations)
+    }
+}
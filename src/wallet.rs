@@ -0,0 +1,110 @@
+use crate::{Authority, ClientId, OperationError, Outcome, TransferTransaction, WalletId};
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+/// Balances of a single wallet of a client, the client's own balances being
+/// the sum of those of its wallets
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Wallet {
+    client: ClientId,
+    wallet: WalletId,
+    available: Decimal,
+    held: Decimal,
+    total: Decimal,
+    /// Part of the client's debt incurred by the wallet
+    #[serde(skip)]
+    debt: Decimal,
+}
+
+impl Wallet {
+    pub(crate) fn new(client: ClientId, wallet: WalletId) -> Self {
+        Self {
+            client,
+            wallet,
+            available: Decimal::new(0, 4),
+            held: Decimal::new(0, 4),
+            total: Decimal::new(0, 4),
+            debt: Decimal::new(0, 4),
+        }
+    }
+
+    pub fn client(&self) -> ClientId {
+        self.client
+    }
+
+    pub fn wallet(&self) -> WalletId {
+        self.wallet
+    }
+
+    pub fn available(&self) -> &Decimal {
+        &self.available
+    }
+
+    pub fn held(&self) -> &Decimal {
+        &self.held
+    }
+
+    pub fn total(&self) -> &Decimal {
+        &self.total
+    }
+
+    pub fn debt(&self) -> &Decimal {
+        &self.debt
+    }
+
+    /// Adds the given changes to each balance
+    pub(crate) fn adjust(&mut self, available: Decimal, held: Decimal, total: Decimal) {
+        self.available += available;
+        self.held += held;
+        self.total += total;
+    }
+
+    /// Moves `amount` between the available balance and debt, where a
+    /// negative amount repays debt
+    pub(crate) fn adjust_debt(&mut self, amount: Decimal) {
+        self.available += amount;
+        self.debt += amount;
+    }
+}
+
+impl Authority {
+    /// Applies internal moves between wallets of a client
+    ///
+    /// Exact resubmissions of a transfer are ignored as duplicates, whereas
+    /// any other reuse of its tx is a conflict.
+    pub(crate) fn apply_transfer(
+        &mut self,
+        t: TransferTransaction,
+    ) -> Result<Outcome, OperationError> {
+        if let Some(existing) = self.transfers.get(&t.tx()) {
+            let duplicate = existing.client() == t.client()
+                && existing.amount() == t.amount()
+                && existing.from() == t.from()
+                && existing.to() == t.to();
+
+            return match duplicate {
+                true => Ok(Outcome::Duplicate),
+                false => Err(OperationError::TransactionConflict(t.tx())),
+            };
+        }
+        if self.tx_taken(t.tx()) || self.transaction_ledger.get(&t.tx())?.is_some() {
+            return Err(OperationError::TransactionExists(t.tx()));
+        }
+
+        let Some(client) = self.client_state.get_mut(&t.client()) else {
+            return Err(OperationError::UnknownClient(t.tx(), t.client()));
+        };
+
+        client.apply_transfer(&t)?;
+        self.transfers.insert(t.tx(), t);
+        Ok(Outcome::Applied)
+    }
+
+    /// Reports the balances of every wallet, in order of client and wallet
+    pub fn wallets(&self) -> Vec<Wallet> {
+        self.client_state
+            .values()
+            .flat_map(|client| client.wallets().cloned())
+            .collect()
+    }
+}
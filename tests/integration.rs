@@ -1,6 +1,15 @@
-use credit::{transcode, Authority};
+use credit::{
+    client_row, decompress, detect_delimiter, transcode, transcode_lines, transcode_mapped,
+    try_transcode, verify_audit, AuditError, AuditLog, Authority, CollectionPolicy, Column,
+    ColumnMapping, Compression, DisputeTransaction, DisputeTransactionType, Encoder, Field, Merge,
+    MergeOrder, MerkleProof, MerkleTree, Transaction,
+};
 use csv::Writer;
 use pretty_assertions::assert_eq;
+use std::{
+    io::{Read, Write},
+    time::Duration,
+};
 
 #[test]
 fn integration() {
@@ -10,6 +19,7 @@ fn integration() {
         .from_reader(std::include_str!("./sample.csv").as_bytes());
 
     let mut authority = Authority::from_iter(transcode(rdr));
+    assert!(authority.verify().unwrap().is_empty());
 
     let mut wtr = Writer::from_writer(vec![]);
     for client in authority.iter_clients() {
@@ -27,3 +37,553 @@ fn integration() {
         data,
     )
 }
+
+#[test]
+fn timestamps() {
+    let rdr = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(
+            "type, client, tx, amount, timestamp
+deposit, 1, 1, 1.0, 0
+deposit, 1, 2, 2.0, 86400
+dispute, 1, 1, , 86401
+dispute, 1, 2"
+                .as_bytes(),
+        );
+
+    let mut authority = Authority::default().with_dispute_window(Duration::from_secs(86400));
+    authority.apply_iter(transcode(rdr).into_iter());
+
+    let mut wtr = Writer::from_writer(vec![]);
+    for client in authority.iter_clients() {
+        wtr.serialize(client).unwrap();
+    }
+
+    let data = String::from_utf8(wtr.into_inner().unwrap()).unwrap();
+
+    assert_eq!(
+        "client,available,held,total,locked
+1,1.0000,2.0000,3.0000,false
+",
+        data,
+    )
+}
+
+#[test]
+fn identifiers() {
+    let rdr = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(
+            "type, client, tx, amount
+deposit, 18446744073709551615, 18446744073709551615, 1.0
+deposit, 18446744073709551615, 67E55044-10B1-426F-9247-BB680E5FE0C8, 2.0
+deposit, 18446744073709551616, 1, 4.0
+deposit, 1, 18446744073709551616, 4.0
+deposit, 1, not-a-uuid, 4.0
+dispute, 18446744073709551615, 67e5504410b1426f9247bb680e5fe0c8"
+                .as_bytes(),
+        );
+
+    let transactions = transcode(rdr).into_iter().collect::<Vec<_>>();
+    assert_eq!(
+        vec![
+            "deposit,18446744073709551615,18446744073709551615,1.0000,,,,0,,",
+            "deposit,18446744073709551615,67e55044-10b1-426f-9247-bb680e5fe0c8,2.0000,,,,0,,",
+            "dispute,18446744073709551615,67e55044-10b1-426f-9247-bb680e5fe0c8,,,,,,,",
+        ],
+        transactions
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+    );
+
+    let mut authority = Authority::from_iter(transactions);
+    let mut wtr = Writer::from_writer(vec![]);
+    for client in authority.iter_clients() {
+        wtr.serialize(client).unwrap();
+    }
+
+    let data = String::from_utf8(wtr.into_inner().unwrap()).unwrap();
+
+    assert_eq!(
+        "client,available,held,total,locked
+18446744073709551615,1.0000,2.0000,3.0000,false
+",
+        data,
+    )
+}
+
+#[test]
+fn column_mapping() {
+    let partner = "kind;account;ref;value
+deposit;1;1;1.0
+deposit;2;2;2.0
+withdrawal;1;3;0.5
+dispute;2;2;
+";
+    assert_eq!(b';', detect_delimiter(partner.as_bytes()));
+
+    let mapping = ColumnMapping::from_reader(csv::Reader::from_reader(
+        "field,column
+type,kind
+client,account
+tx,ref
+amount,value"
+            .as_bytes(),
+    ))
+    .unwrap();
+    let rdr = csv::ReaderBuilder::new()
+        .flexible(true)
+        .delimiter(b';')
+        .from_reader(partner.as_bytes());
+    let mapped = transcode_mapped(rdr, &mapping)
+        .unwrap()
+        .into_iter()
+        .map(|t| t.to_string())
+        .collect::<Vec<_>>();
+
+    let headerless = "1\tdeposit\t1\t1.0
+2\tdeposit\t2\t2.0
+1\twithdrawal\t3\t0.5
+2\tdispute\t2
+";
+    assert_eq!(b'\t', detect_delimiter(headerless.as_bytes()));
+
+    let mapping = ColumnMapping::default()
+        .with_column(Field::Type, Column::Index(1))
+        .with_column(Field::Client, Column::Index(0))
+        .with_column(Field::Tx, Column::Index(2))
+        .with_column(Field::Amount, Column::Index(3));
+    let rdr = csv::ReaderBuilder::new()
+        .flexible(true)
+        .delimiter(b'\t')
+        .has_headers(false)
+        .from_reader(headerless.as_bytes());
+    let indexed = transcode_mapped(rdr, &mapping)
+        .unwrap()
+        .into_iter()
+        .map(|t| t.to_string())
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        vec![
+            "deposit,1,1,1.0000,,,,0,,",
+            "deposit,2,2,2.0000,,,,0,,",
+            "withdrawal,1,3,0.5000,,,,0,,",
+            "dispute,2,2,,,,,,,",
+        ],
+        mapped
+    );
+    assert_eq!(mapped, indexed);
+
+    // Columns mapped by name must be present
+    let rdr = csv::ReaderBuilder::new()
+        .delimiter(b';')
+        .from_reader(partner.as_bytes());
+    assert!(transcode_mapped(rdr, &ColumnMapping::default()).is_err());
+}
+
+#[test]
+fn dispute_reasons() {
+    let input = "type,client,tx,amount,reason,reference
+deposit,1,1,10.0,,
+deposit,1,2,5.0,,
+deposit,2,3,8.0,,
+dispute,1,1,4.0,10.4,CASE-1
+chargeback,1,1,,,
+dispute,2,3,,13.1,
+resolve,2,3,1.0,,
+resolve,2,3,8.0,,
+dispute,1,2,,10.4,CASE-2
+dispute,2,1,,,
+";
+    let rdr = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(input.as_bytes());
+    let authority = Authority::from_iter(transcode(rdr));
+
+    let history = authority
+        .iter_dispute_history()
+        .map(|t| (t.reason(), t.reference(), t.amount().map(|a| a.to_string())))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vec![
+            (Some("10.4"), Some("CASE-1"), Some("4.0000".to_string())),
+            (None, None, Some("4.0000".to_string())),
+            (Some("13.1"), None, Some("8.0000".to_string())),
+            (None, None, Some("8.0000".to_string())),
+            (Some("10.4"), Some("CASE-2"), Some("5.0000".to_string())),
+            (None, None, Some("6.0000".to_string())),
+        ],
+        history
+    );
+
+    let mut wtr = Writer::from_writer(vec![]);
+    for summary in authority.dispute_reasons() {
+        wtr.serialize(summary).unwrap();
+    }
+    assert_eq!(
+        "reason,disputes,disputed,chargebacks,charged_back
+,1,6.0000,0,0.0000
+10.4,2,9.0000,1,4.0000
+13.1,1,8.0000,0,0.0000
+",
+        String::from_utf8(wtr.into_inner().unwrap()).unwrap()
+    );
+}
+
+#[test]
+fn wallets() {
+    let input = "type,client,tx,amount,wallet,to_wallet
+deposit,1,1,10.0,,
+deposit,1,2,5.0,1,
+transfer,1,3,4.0,,1
+transfer,1,3,4.0,,1
+transfer,1,1,1.0,,1
+deposit,1,3,4.0,,
+withdrawal,1,4,7.0,,
+withdrawal,1,5,7.0,1,
+dispute,1,2,,,
+transfer,1,6,1.0,1,0
+";
+    let rdr = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(input.as_bytes());
+    let mut authority = Authority::from_iter(transcode(rdr));
+
+    // Disputes hold funds in the wallet of the disputed transaction
+    let mut wtr = Writer::from_writer(vec![]);
+    for wallet in authority.wallets() {
+        wtr.serialize(wallet).unwrap();
+    }
+    assert_eq!(
+        "client,wallet,available,held,total
+1,0,6.0000,0.0000,6.0000
+1,1,-3.0000,5.0000,2.0000
+",
+        String::from_utf8(wtr.into_inner().unwrap()).unwrap()
+    );
+    assert_eq!(
+        "1,3.0000,5.0000,8.0000,false",
+        client_row(authority.iter_clients().next().unwrap())
+    );
+    assert!(authority.verify().unwrap().is_empty());
+}
+
+#[test]
+fn compression() {
+    let sample = std::include_str!("./sample.csv");
+
+    for compression in [Compression::Gzip, Compression::Zstd] {
+        let mut encoder = Encoder::new(vec![], Some(compression)).unwrap();
+        encoder.write_all(sample.as_bytes()).unwrap();
+        let compressed = encoder.finish().unwrap();
+        assert_eq!(Some(compression), Compression::from_magic(&compressed));
+
+        let mut decompressed = String::new();
+        decompress(compressed.as_slice(), None)
+            .unwrap()
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert_eq!(sample, decompressed);
+    }
+
+    assert_eq!(
+        Some(Compression::Zstd),
+        Compression::from_extension("daily.csv.zst")
+    );
+    assert_eq!(None, Compression::from_extension("daily.csv"));
+
+    // Uncompressed input is passed through
+    let mut plain = String::new();
+    decompress(sample.as_bytes(), None)
+        .unwrap()
+        .read_to_string(&mut plain)
+        .unwrap();
+    assert_eq!(sample, plain);
+}
+
+#[test]
+fn merge() {
+    let card = "type, client, tx, amount, timestamp
+deposit, 1, 1, 1.0, 10
+deposit, 1, 2, 1.0, 30
+dispute, 1, 1, , 40";
+    let bank = "type, client, tx, amount, timestamp
+deposit, 2, 3, 2.0, 20
+deposit, 2, 4, 2.0, 25";
+
+    let merged = |order| {
+        let mut merge = Merge::new(order);
+        for (name, data) in [("card.csv", card), ("bank.csv", bank)] {
+            let rdr = csv::ReaderBuilder::new()
+                .flexible(true)
+                .trim(csv::Trim::All)
+                .from_reader(data.as_bytes());
+            let rows = transcode_lines(rdr, &ColumnMapping::default(), name).unwrap();
+            merge = merge.with_source(name, rows);
+        }
+
+        merge
+            .map(|(provenance, t)| format!("{} {}", provenance, t.tx()))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        vec![
+            "card.csv:2 1",
+            "card.csv:3 2",
+            "card.csv:4 1",
+            "bank.csv:2 3",
+            "bank.csv:3 4",
+        ],
+        merged(MergeOrder::Sequential)
+    );
+    assert_eq!(
+        vec![
+            "card.csv:2 1",
+            "bank.csv:2 3",
+            "card.csv:3 2",
+            "bank.csv:3 4",
+            "card.csv:4 1",
+        ],
+        merged(MergeOrder::RoundRobin)
+    );
+    assert_eq!(
+        vec![
+            "card.csv:2 1",
+            "bank.csv:2 3",
+            "bank.csv:3 4",
+            "card.csv:3 2",
+            "card.csv:4 1",
+        ],
+        merged(MergeOrder::Timestamp)
+    );
+}
+
+#[test]
+fn strict() {
+    let rdr = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(
+            "type,client,tx,amount
+deposit,1,1,1.0
+deposit,1,2,1.0.0
+deposit,1,3"
+                .as_bytes(),
+        );
+
+    let mut rows = try_transcode(rdr, &ColumnMapping::default()).unwrap();
+    assert_eq!(
+        "deposit,1,1,1.0000,,,,0,,",
+        rows.next().unwrap().unwrap().1.to_string()
+    );
+
+    let e = rows.next().unwrap().unwrap_err();
+    assert_eq!((38, 3, Some(4)), (e.byte(), e.line(), e.column()));
+    assert_eq!(
+        "line 3, column 4 (byte 38): Invalid amount: 1.0.0",
+        e.to_string()
+    );
+
+    let e = rows.next().unwrap().unwrap_err();
+    assert_eq!((56, 4, Some(4)), (e.byte(), e.line(), e.column()));
+    assert!(rows.next().is_none());
+}
+
+#[test]
+fn sqlite() {
+    let rdr = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(std::include_str!("./sample.csv").as_bytes());
+
+    let authority = Authority::from_iter(transcode(rdr));
+
+    let db = tempfile::NamedTempFile::new().unwrap();
+    authority.export_sqlite(db.path()).unwrap();
+
+    let conn = rusqlite::Connection::open(db.path()).unwrap();
+    let clients = conn
+        .prepare(
+            "SELECT client FROM clients c
+            WHERE available < 0 AND EXISTS (
+                SELECT 1 FROM disputes d
+                JOIN transactions t ON t.tx = d.tx
+                WHERE t.client = c.client
+            )",
+        )
+        .unwrap()
+        .query_map([], |row| row.get::<_, String>(0))
+        .unwrap()
+        .collect::<Result<Vec<String>, _>>()
+        .unwrap();
+    assert_eq!(vec!["1"], clients);
+
+    let history = conn
+        .query_row("SELECT COUNT(*) FROM dispute_history", [], |row| {
+            row.get::<_, u32>(0)
+        })
+        .unwrap();
+    assert_eq!(4, history);
+
+    // Debt moved out of available balances is exported alongside them
+    let rdr = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(std::include_str!("./sample.csv").as_bytes());
+    let mut authority = Authority::default().with_collections(CollectionPolicy::Full);
+    authority.apply_iter(transcode(rdr).into_iter());
+
+    let db = tempfile::NamedTempFile::new().unwrap();
+    authority.export_sqlite(db.path()).unwrap();
+
+    let conn = rusqlite::Connection::open(db.path()).unwrap();
+    let unbalanced = conn
+        .query_row(
+            "SELECT COUNT(*) FROM clients WHERE available + held - debt != total",
+            [],
+            |row| row.get::<_, u32>(0),
+        )
+        .unwrap();
+    let debt = conn
+        .query_row("SELECT debt FROM clients WHERE client = '2'", [], |row| {
+            row.get::<_, f64>(0)
+        })
+        .unwrap();
+    assert_eq!((0, 3.0), (unbalanced, debt));
+}
+
+#[test]
+fn sqlite_ids() {
+    let rdr = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(
+            "type,client,tx,amount,timestamp\n\
+            deposit,18446744073709551615,18446744073709551615,1.0,18446744073709551615\n"
+                .as_bytes(),
+        );
+
+    let authority = Authority::from_iter(transcode(rdr));
+    let db = tempfile::NamedTempFile::new().unwrap();
+    authority.export_sqlite(db.path()).unwrap();
+
+    let conn = rusqlite::Connection::open(db.path()).unwrap();
+    let (client, tx, timestamp) = conn
+        .query_row(
+            "SELECT client, tx, timestamp FROM transactions",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .unwrap();
+    let max = u64::MAX.to_string();
+    assert_eq!((&max, &max, &max), (&client, &tx, &timestamp));
+}
+
+#[test]
+fn audit() {
+    let rdr = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(std::include_str!("./sample.csv").as_bytes());
+
+    let mut authority = Authority::default();
+    let mut log = AuditLog::new(vec![]).unwrap();
+    for t in transcode(rdr) {
+        let res = authority.apply(t.clone());
+        log.record(&t, &res).unwrap();
+    }
+
+    let head = log.head();
+    let data = String::from_utf8(log.into_inner().unwrap()).unwrap();
+    assert_eq!(11, data.lines().count());
+
+    let verify = |data: &str, expected: Option<&str>| {
+        verify_audit(csv::Reader::from_reader(data.as_bytes()), expected)
+    };
+    assert_eq!(head, verify(&data, Some(&head)).unwrap());
+
+    // Altered amount
+    let altered = data.replacen("4.0000", "5.0000", 1);
+    assert!(matches!(
+        verify(&altered, None),
+        Err(AuditError::Tampered(2))
+    ));
+
+    // Removed record
+    let removed = data
+        .lines()
+        .enumerate()
+        .filter(|(i, _)| *i != 3)
+        .map(|(_, l)| format!("{}\n", l))
+        .collect::<String>();
+    assert!(matches!(
+        verify(&removed, None),
+        Err(AuditError::Malformed(3))
+    ));
+
+    // Truncated log is only detected against the expected head
+    let truncated = data.lines().take(10).collect::<Vec<_>>().join("\n");
+    assert!(verify(&truncated, None).is_ok());
+    assert!(matches!(
+        verify(&truncated, Some(&head)),
+        Err(AuditError::HeadMismatch(..))
+    ));
+
+    // Records differing only in where one field ends and the next begins
+    let head = |reason: &str, reference: &str| {
+        let t = Transaction::Dispute(
+            DisputeTransaction::new(DisputeTransactionType::Dispute, 1, 1.into())
+                .with_reason(reason)
+                .with_reference(reference),
+        );
+        let mut log = AuditLog::new(vec![]).unwrap();
+        log.record(&t, &Authority::default().apply(t.clone()))
+            .unwrap();
+        log.head()
+    };
+    assert_ne!(head("a\x1fb", ""), head("a", "b"));
+}
+
+#[test]
+fn merkle() {
+    let rdr = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(
+            "type, client, tx, amount
+deposit, 1, 1, 1.0
+deposit, 2, 2, 2.0
+deposit, 3, 3, 3.0
+deposit, 4, 4, 4.0
+deposit, 5, 5, 5.0"
+                .as_bytes(),
+        );
+
+    let mut authority = Authority::from_iter(transcode(rdr));
+    let tree = MerkleTree::new(authority.iter_clients());
+    let root = tree.root();
+
+    for client in authority.iter_clients() {
+        let row = client_row(client);
+        let proof = tree.proof(client.id()).unwrap();
+        assert!(proof.verify(&root, &row));
+
+        // Proofs survive their exported form
+        let proof = proof.to_string().parse::<MerkleProof>().unwrap();
+        assert!(proof.verify(&root, &row));
+
+        // Any other balance is rejected
+        assert!(!proof.verify(&root, &row.replace(".0000", ".0001")));
+    }
+
+    assert_eq!(
+        "1,1.0000,0.0000,1.0000,false",
+        client_row(authority.iter_clients().next().unwrap())
+    );
+    assert!(tree.proof(6).is_none());
+}